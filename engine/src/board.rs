@@ -0,0 +1,296 @@
+// the stack: one occupancy bitmask per row for collisions and line clears,
+// and the colour codes of the cells alongside for rendering
+
+// columns that fit in a row mask
+pub const MAX_COLS: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct Board {
+    cols: usize,
+    // bit i set when column i is taken, like the piece shapes; row 0 is the top
+    occupancy: Vec<u64>,
+    // colour code of each cell row by row, 0 for empty ones
+    colors: Vec<u8>,
+}
+
+impl Board {
+    // `cols` goes from 1 to MAX_COLS, `GameConfig::validate` checks it for games
+    pub fn new(cols: usize, rows: usize) -> Board {
+        assert!(
+            (1..=MAX_COLS).contains(&cols),
+            "the board is 1 to {} columns wide",
+            MAX_COLS
+        );
+        Board {
+            cols,
+            occupancy: vec![0; rows],
+            colors: vec![0; cols * rows],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cols
+    }
+
+    pub fn height(&self) -> usize {
+        self.occupancy.len()
+    }
+
+    // mask of a row with every column taken
+    fn full_row(&self) -> u64 {
+        u64::MAX >> (MAX_COLS - self.cols)
+    }
+
+    // occupancy mask of row `y`
+    pub fn row(&self, y: usize) -> u64 {
+        self.occupancy[y]
+    }
+
+    // colour codes of row `y`
+    pub fn line(&self, y: usize) -> &[u8] {
+        &self.colors[y * self.cols..(y + 1) * self.cols]
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.colors[y * self.cols + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, code: u8) {
+        self.colors[y * self.cols + x] = code;
+        if code == 0 {
+            self.occupancy[y] &= !(1 << x);
+        } else {
+            self.occupancy[y] |= 1 << x;
+        }
+    }
+
+    // true for cells taken by the stack or outside of the board
+    pub fn is_occupied(&self, x: isize, y: isize) -> bool {
+        x < 0
+            || y < 0
+            || x as usize >= self.cols
+            || y as usize >= self.height()
+            || self.occupancy[y as usize] & (1 << x) != 0
+    }
+
+    // true if a shape, one bitmask per row, overlaps the stack or the walls
+    // with its top left corner at (`x`, `y`)
+    pub fn collides(&self, shape: &[u8], x: isize, y: isize) -> bool {
+        for (dy, &line) in shape.iter().enumerate() {
+            if line == 0 {
+                continue;
+            }
+            let row = y + dy as isize;
+            if row < 0 || row as usize >= self.height() {
+                return true;
+            }
+            let line = line as u64;
+            let mask = if x < 0 {
+                // blocks shifted out to the right of bit 0 are past the left wall
+                if x <= -(u8::BITS as isize) || line & ((1 << -x) - 1) != 0 {
+                    return true;
+                }
+                line >> -x
+            } else {
+                if x as usize >= MAX_COLS || (line << x) >> x != line {
+                    return true;
+                }
+                line << x
+            };
+            if mask & !self.full_row() != 0 || mask & self.occupancy[row as usize] != 0 {
+                return true;
+            }
+        }
+        false
+    }
+
+    // number of rows holding at least one cell of colour `code`
+    pub fn count_rows_with(&self, code: u8) -> usize {
+        (0..self.height())
+            .filter(|&y| self.line(y).contains(&code))
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.occupancy.iter().all(|&row| row == 0)
+    }
+
+    // removes full rows, moving the ones above down, returns how many there were
+    pub fn clear_full_rows(&mut self) -> u32 {
+        let full = self.full_row();
+        let mut write = self.height();
+        for read in (0..self.height()).rev() {
+            if self.occupancy[read] == full {
+                continue;
+            }
+            write -= 1;
+            if write != read {
+                self.occupancy[write] = self.occupancy[read];
+                self.colors
+                    .copy_within(read * self.cols..(read + 1) * self.cols, write * self.cols);
+            }
+        }
+        self.occupancy[..write].fill(0);
+        self.colors[..write * self.cols].fill(0);
+        write as u32
+    }
+
+    // pushes everything up a row and fills the bottom one with `code` but for column `hole`;
+    // returns true if blocks were pushed off the top, they are lost
+    pub fn push_row(&mut self, code: u8, hole: usize) -> bool {
+        let lost = self.occupancy[0] != 0;
+        self.occupancy.rotate_left(1);
+        self.colors.rotate_left(self.cols);
+
+        let bottom = self.height() - 1;
+        for x in 0..self.cols {
+            self.set(x, bottom, if x == hole { 0 } else { code });
+        }
+        lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::pieces::PIECE_KINDS;
+
+    // the map as it was stored before the bitboard, one vector per row
+    struct Reference {
+        cols: usize,
+        map: Vec<Vec<u8>>,
+    }
+
+    impl Reference {
+        fn is_occupied(&self, x: isize, y: isize) -> bool {
+            x < 0
+                || y < 0
+                || x as usize >= self.cols
+                || y as usize >= self.map.len()
+                || self.map[y as usize][x as usize] != 0
+        }
+
+        fn collides(&self, shape: &[u8], x: isize, y: isize) -> bool {
+            shape.iter().enumerate().any(|(dy, line)| {
+                (0..8).any(|dx| line & (1 << dx) != 0 && self.is_occupied(x + dx, y + dy as isize))
+            })
+        }
+
+        fn clear_full_rows(&mut self) -> u32 {
+            let rows = self.map.len();
+            self.map.retain(|line| line.contains(&0));
+            let lines = rows - self.map.len();
+            for _ in 0..lines {
+                self.map.insert(0, vec![0; self.cols]);
+            }
+            lines as u32
+        }
+
+        fn push_row(&mut self, code: u8, hole: usize) -> bool {
+            let lost = self.map.remove(0).iter().any(|&case| case != 0);
+            let mut line = vec![code; self.cols];
+            if let Some(case) = line.get_mut(hole) {
+                *case = 0;
+            }
+            self.map.push(line);
+            lost
+        }
+    }
+
+    // the same random stack in both layouts, some rows full
+    fn random_boards(rng: &mut ChaCha8Rng, cols: usize, rows: usize) -> (Board, Reference) {
+        let mut board = Board::new(cols, rows);
+        let mut reference = Reference {
+            cols,
+            map: vec![vec![0; cols]; rows],
+        };
+        for y in rows / 2..rows {
+            let full = rng.gen_bool(0.3);
+            for x in 0..cols {
+                if full || rng.gen_bool(0.6) {
+                    let code = rng.gen_range(1..=8);
+                    board.set(x, y, code);
+                    reference.map[y][x] = code;
+                }
+            }
+        }
+        (board, reference)
+    }
+
+    fn assert_same(board: &Board, reference: &Reference) {
+        for (y, line) in reference.map.iter().enumerate() {
+            assert_eq!(board.line(y), &line[..], "row {}", y);
+            let mask = line
+                .iter()
+                .enumerate()
+                .filter(|(_, &case)| case != 0)
+                .fold(0, |mask, (x, _)| mask | 1 << x);
+            assert_eq!(board.row(y), mask, "row {}", y);
+        }
+    }
+
+    #[test]
+    fn collisions_match_the_vector_map() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for cols in [4, 10, 63, 64] {
+            let (board, reference) = random_boards(&mut rng, cols, 12);
+            for kind in PIECE_KINDS {
+                for rot in 0..4 {
+                    for x in -5..cols as isize + 2 {
+                        for y in -5..14 {
+                            assert_eq!(
+                                board.collides(kind.shape(rot), x, y),
+                                reference.collides(kind.shape(rot), x, y),
+                                "{:?} rot {} at ({}, {}) on {} columns",
+                                kind,
+                                rot,
+                                x,
+                                y,
+                                cols
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clears_match_the_vector_map() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for cols in [1, 4, 10, 64] {
+            for _ in 0..20 {
+                let (mut board, mut reference) = random_boards(&mut rng, cols, 12);
+                assert_eq!(board.clear_full_rows(), reference.clear_full_rows());
+                assert_same(&board, &reference);
+            }
+        }
+    }
+
+    #[test]
+    fn pushed_rows_match_the_vector_map() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for cols in [1, 4, 10, 64] {
+            let (mut board, mut reference) = random_boards(&mut rng, cols, 12);
+            for _ in 0..15 {
+                let hole = rng.gen_range(0..cols + 1);
+                assert_eq!(board.push_row(8, hole), reference.push_row(8, hole));
+                assert_same(&board, &reference);
+            }
+        }
+    }
+
+    #[test]
+    fn widest_board_clears_its_full_row() {
+        let mut board = Board::new(MAX_COLS, 2);
+        for x in 0..MAX_COLS {
+            board.set(x, 1, 1);
+        }
+        assert_eq!(board.row(1), u64::MAX);
+        assert_eq!(board.clear_full_rows(), 1);
+        assert!(board.is_empty());
+    }
+}
@@ -0,0 +1,1312 @@
+use std::{collections::VecDeque, str::FromStr, time::SystemTime};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    board::{Board, MAX_COLS},
+    game::GameState::{End, Finished, Playing, Start},
+    modes::{GameMode, CREDITS_SECONDS, DIG_FEED_ROWS, SPLIT_LINES},
+    pieces::{Piece, PieceKind, PIECEWIDTH},
+    randomizer::{Randomizer, RandomizerKind},
+    rotation::{Rotation, RotationSystem, RotationSystemKind},
+    rules::{Level, Rules},
+    scoring::{Clear, LockResult, Scoring, Spin},
+};
+// default size of the map
+pub const GAMEMAP_ROWS: usize = 20;
+pub const GAMEMAP_COLS: usize = 14;
+// hidden rows above the map where pieces spawn
+pub const BUFFER_ROWS: usize = 20;
+
+pub const MAX_PREVIEW: usize = 6;
+
+// cell code of garbage rows, drawn with the last colour of the palette
+pub const GARBAGE: u8 = 8;
+const GARBAGE_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// the game advances in fixed steps, `Game::update` is called once per frame
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+// what gives a grounded piece more time before it locks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LockReset {
+    // only reaching a lower row
+    Step,
+    // any successful move or rotation, up to `max_lock_resets` times per row
+    #[default]
+    Move,
+    // any successful move or rotation, without limit
+    Infinity,
+}
+
+impl FromStr for LockReset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LockReset, String> {
+        match s {
+            "step" => Ok(LockReset::Step),
+            "move" => Ok(LockReset::Move),
+            "infinity" => Ok(LockReset::Infinity),
+            _ => Err(format!("unknown lock reset mode: {}", s)),
+        }
+    }
+}
+
+// why the game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopOut {
+    // the next piece overlaps the stack where it spawns
+    BlockOut,
+    // a piece locked entirely in the hidden rows
+    LockOut,
+    // garbage pushed the stack past the top of the hidden rows
+    GarbageOut,
+}
+
+impl TopOut {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            TopOut::BlockOut => "Block out",
+            TopOut::LockOut => "Lock out",
+            TopOut::GarbageOut => "Garbage out",
+        }
+    }
+}
+
+// which top-out conditions end the game
+#[derive(Clone, Copy, Debug)]
+pub struct TopOutRules {
+    // when off, a piece that does not fit spawns higher up in the hidden rows instead
+    pub block_out: bool,
+    pub lock_out: bool,
+    // when off, blocks pushed past the top of the map are lost
+    pub garbage_out: bool,
+}
+
+impl Default for TopOutRules {
+    fn default() -> TopOutRules {
+        TopOutRules {
+            block_out: true,
+            lock_out: true,
+            garbage_out: true,
+        }
+    }
+}
+
+// rules chosen when the game is created
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    pub seed: u64,
+    pub cols: usize,
+    // visible rows, the map is `buffer_rows` taller
+    pub rows: usize,
+    pub buffer_rows: usize,
+    pub randomizer: RandomizerKind,
+    // number of upcoming pieces visible in the queue, up to MAX_PREVIEW
+    pub preview: usize,
+    // classic rule sets play without the hold slot
+    pub hold: bool,
+    pub rotation: RotationSystemKind,
+    pub lock_reset: LockReset,
+    pub max_lock_resets: u32,
+    // gravity and delays of each level
+    pub rules: Rules,
+    pub top_out: TopOutRules,
+    pub mode: GameMode,
+}
+
+impl GameConfig {
+    // rows of the map including the hidden ones
+    pub fn map_rows(&self) -> usize {
+        self.rows + self.buffer_rows
+    }
+
+    // checks the options `Game::new` cannot play with
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cols < PIECEWIDTH || self.rows < PIECEWIDTH {
+            return Err(format!(
+                "the map must be at least {}x{}",
+                PIECEWIDTH, PIECEWIDTH
+            ));
+        }
+        if self.cols > MAX_COLS {
+            return Err(format!("the map is at most {} columns wide", MAX_COLS));
+        }
+        if let GameMode::Dig {
+            rows, feed: false, ..
+        } = self.mode
+        {
+            if rows > self.garbage_room() {
+                return Err(format!(
+                    "{} rows of garbage do not fit on the map, at most {} without feeding",
+                    rows,
+                    self.garbage_room()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // garbage rows the map holds with the two rows pieces spawn on left free
+    pub fn garbage_room(&self) -> u32 {
+        (self.rows as u32).saturating_sub(2)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            seed: 0,
+            cols: GAMEMAP_COLS,
+            rows: GAMEMAP_ROWS,
+            buffer_rows: BUFFER_ROWS,
+            randomizer: RandomizerKind::default(),
+            preview: 3,
+            hold: true,
+            rotation: RotationSystemKind::default(),
+            lock_reset: LockReset::default(),
+            max_lock_resets: 15,
+            rules: Rules::default(),
+            top_out: TopOutRules::default(),
+            mode: GameMode::default(),
+        }
+    }
+}
+
+// lock delay bookkeeping of the falling piece
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LockState {
+    // frames spent on the ground since the last reset
+    pub frames: u32,
+    // resets used since the piece reached its lowest row
+    pub resets: u32,
+    pub lowest_y: isize,
+}
+
+pub enum GameState {
+    Start,
+    Playing,
+    Paused,
+    // topped out
+    End,
+    // reached the goal of the game mode
+    Finished,
+}
+pub struct Game {
+    pub board: Board,
+    pub level: u32,
+    pub score: u32,
+    pub lines_cleared: u32,
+    pub scoring: Scoring,
+    pub last_lock: Option<LockResult>,
+    pub piece: Piece,
+    // false while the next piece waits out the entry delay, and once the game is over
+    pub piece_moves: bool,
+    pub lock: LockState,
+    // set by a rotation, true if its kick makes a T-spin a full one; cleared when the piece moves
+    pub last_rotation: Option<bool>,
+    pub config: GameConfig,
+    randomizer: Box<dyn Randomizer>,
+    queue: VecDeque<PieceKind>,
+    rotation_system: Box<dyn RotationSystem>,
+    pub held: Option<PieceKind>,
+    // set once a piece was held, cleared when a piece locks
+    pub hold_used: bool,
+    // frames left before the next piece appears
+    pub spawn_delay: u32,
+    // fraction of a row the piece has fallen since its last step down
+    gravity_progress: f32,
+    // gravity multiplier while the soft drop key is held
+    pub soft_drop: Option<u32>,
+    pub hiscores: [u32; 5],
+    pub lines: [u32; 5],
+    pub current_state: GameState,
+    // set when the game is over
+    pub top_out: Option<TopOut>,
+    // time played when each multiple of SPLIT_LINES lines was reached, in milliseconds
+    pub splits: Vec<u128>,
+    // frames left in the credits roll, while it is played
+    pub credits_left: Option<u32>,
+    // garbage rows of dig mode not on the board yet
+    pub garbage_pending: u32,
+    // column of the last garbage hole
+    garbage_hole: Option<usize>,
+    garbage_rng: ChaCha8Rng,
+    // best result of the mode so far, to compare with, see `Game::result`
+    pub personal_best: Option<u128>,
+    pub total_time_played: u128,
+    pub time_measure_start: SystemTime,
+}
+
+impl Game {
+    // default rules on a map `cols` wide and `rows` high
+    pub fn with_size(cols: usize, rows: usize) -> Result<Game, String> {
+        Game::new(GameConfig {
+            cols,
+            rows,
+            ..Default::default()
+        })
+    }
+
+    pub fn new(mut config: GameConfig) -> Result<Game, String> {
+        config.validate()?;
+        config.preview = config.preview.min(MAX_PREVIEW);
+        let mut randomizer = config.randomizer.build(config.seed);
+        let first = randomizer.next();
+        let queue = (0..config.preview).map(|_| randomizer.next()).collect();
+        let rotation_system = config.rotation.build();
+        // its own stream so garbage does not change the piece sequence
+        let garbage_rng = ChaCha8Rng::seed_from_u64(config.seed ^ GARBAGE_SEED);
+        let level = match config.mode {
+            GameMode::Zen { level } => level.max(1),
+            _ => 1,
+        };
+        let mut game = Game {
+            board: Board::new(config.cols, config.map_rows()),
+            level,
+            score: 0,
+            lines_cleared: 0,
+            scoring: Scoring::default(),
+            last_lock: None,
+            piece: Piece::new(first),
+            piece_moves: true,
+            lock: LockState::default(),
+            last_rotation: None,
+            config,
+            randomizer,
+            queue,
+            rotation_system,
+            held: None,
+            hold_used: false,
+            spawn_delay: 0,
+            gravity_progress: 0.0,
+            soft_drop: None,
+            hiscores: [0_u32; 5],
+            lines: [0_u32; 5],
+            current_state: Start,
+            top_out: None,
+            splits: Vec::new(),
+            credits_left: None,
+            garbage_pending: 0,
+            garbage_hole: None,
+            garbage_rng,
+            personal_best: None,
+            total_time_played: 0,
+            time_measure_start: SystemTime::now(),
+        };
+        if let GameMode::Dig { rows, .. } = game.config.mode {
+            game.garbage_pending = rows;
+            game.feed_garbage();
+        }
+        game.spawn_piece(first);
+        Ok(game)
+    }
+
+    // removes full lines from the board, returns how many there were
+    fn collapse(&mut self) -> u32 {
+        let lines = self.board.clear_full_rows();
+
+        for _ in 0..lines {
+            self.lines_cleared += 1;
+
+            if self.lines_cleared >= self.level_rules().lines
+                && self.level < self.config.rules.max_level()
+                && !matches!(self.config.mode, GameMode::Zen { .. })
+            {
+                self.level += 1;
+            }
+        }
+        lines
+    }
+
+    // timings of the current level
+    pub fn level_rules(&self) -> &Level {
+        self.config.rules.level(self.level)
+    }
+
+    // colour codes of the rows shown to the player, below the hidden ones
+    pub fn visible_map(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (self.config.buffer_rows..self.board.height()).map(|y| self.board.line(y))
+    }
+
+    pub fn is_board_empty(&self) -> bool {
+        self.board.is_empty()
+    }
+
+    // 3-corner rule: a T piece locked right after a rotation with three of the four corners
+    // around its center taken; a mini unless both corners it points to are taken, or the
+    // rotation system says the kick makes it a full one
+    fn detect_spin(&self) -> Spin {
+        let p = &self.piece;
+        let Some(full_kick) = self.last_rotation else {
+            return Spin::None;
+        };
+        if p.kind != PieceKind::T {
+            return Spin::None;
+        }
+        // clockwise from top left, so the two corners a state points to are `rot` and `rot + 1`
+        let (px, py) = (p.x + p.kind.pivot().0, p.y + p.kind.pivot().1);
+        let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)]
+            .map(|(dx, dy)| self.board.is_occupied(px + dx, py + dy));
+        if corners.iter().filter(|&&taken| taken).count() < 3 {
+            return Spin::None;
+        }
+        let front = corners[p.rot] && corners[(p.rot + 1) % 4];
+        if front || full_kick {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    // fixes piece on the game map, scores it and creates new piece
+    pub fn fix_piece(&mut self) -> LockResult {
+        let spin = self.detect_spin();
+        let mut hidden = true;
+        for (x, y) in self.piece.cells() {
+            hidden &= y < self.config.buffer_rows as isize;
+            // blocks of a piece pushed above the map have nowhere to go
+            let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else {
+                continue;
+            };
+            if x < self.board.width() && y < self.board.height() {
+                self.board.set(x, y, self.piece.kind.code());
+            }
+        }
+
+        // points and delays use the level the lines were cleared at
+        let level = self.level;
+        let timings = *self.level_rules();
+        let lines = self.collapse();
+        let clear = Clear {
+            lines,
+            spin,
+            perfect: lines > 0 && self.is_board_empty(),
+        };
+        let score = self.scoring.score_lock(clear, level);
+        self.score += score.total();
+
+        self.next_piece();
+        self.hold_used = false;
+        self.gravity_progress = 0.0;
+        self.spawn_delay = timings.entry_delay;
+        if lines > 0 {
+            self.spawn_delay += timings.line_clear_delay;
+        }
+        // the next piece waits out of sight until the delay is over
+        self.piece_moves = self.spawn_delay == 0;
+
+        let top_out = if hidden && self.config.top_out.lock_out {
+            Some(TopOut::LockOut)
+        } else if !self.test_position(None, None, None) {
+            Some(TopOut::BlockOut)
+        } else {
+            None
+        };
+        let result = LockResult {
+            clear,
+            score,
+            top_out,
+        };
+        self.last_lock = Some(result);
+        result
+    }
+
+    // takes the piece at the front of the queue and refills it
+    fn next_piece(&mut self) {
+        self.queue.push_back(self.randomizer.next());
+        let kind = self.queue.pop_front().unwrap();
+        self.spawn_piece(kind);
+    }
+
+    // puts a new piece in the hidden rows, moved a row down at once if there is room, so that
+    // its bottom shows at the top of the map; without block out it climbs over the stack
+    fn spawn_piece(&mut self, kind: PieceKind) {
+        let mut piece = Piece::spawn(
+            kind,
+            self.rotation_system.spawn_state(kind),
+            self.config.cols,
+            self.config.buffer_rows,
+        );
+        if self.fits(Piece {
+            y: piece.y + 1,
+            ..piece
+        }) {
+            piece.y += 1;
+        } else if !self.config.top_out.block_out {
+            while !self.fits(piece) && piece.y > -(PIECEWIDTH as isize) {
+                piece.y -= 1;
+            }
+        }
+        self.piece = piece;
+        self.lock = LockState {
+            lowest_y: piece.y,
+            ..Default::default()
+        };
+        self.last_rotation = None;
+    }
+
+    // orientation pieces of `kind` spawn in with the rotation system of the game
+    pub fn spawn_state(&self, kind: PieceKind) -> usize {
+        self.rotation_system.spawn_state(kind)
+    }
+
+    // types of the upcoming pieces, the next one first
+    pub fn next_pieces(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        self.queue.iter().copied()
+    }
+
+    // swaps the current piece with the held one, or with the next one when the slot is empty,
+    // at most once per lock; the game ends if the piece taken out does not fit
+    pub fn hold_piece(&mut self) {
+        if !self.config.hold || self.hold_used || !self.piece_moves {
+            return;
+        }
+        match self.held.replace(self.piece.kind) {
+            Some(kind) => self.spawn_piece(kind),
+            None => self.next_piece(),
+        }
+        self.hold_used = true;
+        self.gravity_progress = 0.0;
+
+        if !self.test_position(None, None, None) {
+            self.game_over(TopOut::BlockOut);
+        }
+    }
+
+    // raises the stack by one garbage row per entry of `holes`, in order, each one full but
+    // for the column it gives; the falling piece is lifted if it is in the way
+    pub fn add_garbage(&mut self, holes: &[usize]) {
+        for &hole in holes {
+            if self.board.push_row(GARBAGE, hole) && self.config.top_out.garbage_out {
+                self.game_over(TopOut::GarbageOut);
+                return;
+            }
+        }
+        while !self.test_position(None, None, None) && self.piece.y > -(PIECEWIDTH as isize) {
+            self.piece.y -= 1;
+            self.lock.lowest_y -= 1;
+        }
+        if !self.test_position(None, None, None) {
+            self.game_over(TopOut::GarbageOut);
+        }
+    }
+
+    // checks if current or specified position is valid
+    pub fn test_position(
+        &self,
+        rot: Option<usize>,
+        xoffs: Option<isize>,
+        yoffs: Option<isize>,
+    ) -> bool {
+        self.fits(Piece {
+            x: xoffs.unwrap_or(self.piece.x),
+            y: yoffs.unwrap_or(self.piece.y),
+            rot: rot.unwrap_or(self.piece.rot),
+            ..self.piece
+        })
+    }
+
+    fn fits(&self, piece: Piece) -> bool {
+        !self.board.collides(piece.shape(), piece.x, piece.y)
+    }
+
+    // rotates current piece, trying the kicks of the rotation system in order;
+    // returns false if none of them fits
+    pub fn rotate_piece(&mut self, dir: Rotation) -> bool {
+        if !self.piece_moves {
+            return false;
+        }
+        let (kind, from) = (self.piece.kind, self.piece.rot);
+        let to = dir.apply(from);
+        let system = &self.rotation_system;
+        let ((fx, fy), (tx, ty)) = (system.offset(kind, from), system.offset(kind, to));
+        let turned = Piece {
+            x: self.piece.x + tx - fx,
+            y: self.piece.y + ty - fy,
+            rot: to,
+            ..self.piece
+        };
+        let mut kicks = system.kicks(kind, from, dir);
+        if !system.may_kick(&turned, &self.board) {
+            kicks = &kicks[..1];
+        }
+
+        for (kick, &(dx, dy)) in kicks.iter().enumerate() {
+            let kicked = Piece {
+                x: turned.x + dx,
+                y: turned.y + dy,
+                ..turned
+            };
+            if self.fits(kicked) {
+                self.piece = kicked;
+                self.last_rotation = Some(
+                    self.rotation_system
+                        .is_full_spin_kick(kind, from, dir, kick),
+                );
+                self.reset_lock();
+                return true;
+            }
+        }
+        false
+    }
+
+    // row the current piece would land on if dropped straight down
+    pub fn landing_y(&self) -> isize {
+        let mut y = self.piece.y;
+        while self.test_position(None, None, Some(y + 1)) {
+            y += 1;
+        }
+        y
+    }
+
+    // moves current piece and signals if it can move in the given
+    pub fn change_piece_position(&mut self, dx: isize, dy: isize) -> bool {
+        if !self.piece_moves {
+            return false;
+        }
+        let nx = self.piece.x + dx;
+        let ny = self.piece.y + dy;
+        if self.test_position(None, Some(nx), Some(ny)) {
+            self.piece.x = nx;
+            self.piece.y = ny;
+            self.last_rotation = None;
+            self.reset_lock();
+            true
+        } else {
+            false
+        }
+    }
+    // called after the piece moved or turned, gives it more time on the ground
+    fn reset_lock(&mut self) {
+        let lock = &mut self.lock;
+        if self.piece.y > lock.lowest_y {
+            lock.lowest_y = self.piece.y;
+            lock.frames = 0;
+            lock.resets = 0;
+            return;
+        }
+        match self.config.lock_reset {
+            LockReset::Step => {}
+            LockReset::Move => {
+                // moves in the air do not use up resets
+                if lock.frames > 0 && lock.resets < self.config.max_lock_resets {
+                    lock.frames = 0;
+                    lock.resets += 1;
+                }
+            }
+            LockReset::Infinity => lock.frames = 0,
+        }
+    }
+
+    // advances the game by one frame: counts down the entry delay, applies gravity, then
+    // counts down the lock delay of a grounded piece and locks it when it runs out
+    pub fn update(&mut self) {
+        if self
+            .config
+            .mode
+            .time_limit()
+            .is_some_and(|limit| self.time_played() >= limit)
+        {
+            self.finish();
+            return;
+        }
+        if let Some(frames) = self.credits_left {
+            if frames == 0 {
+                self.finish();
+                return;
+            }
+            self.credits_left = Some(frames - 1);
+        }
+        if self.spawn_delay > 0 {
+            self.spawn_delay -= 1;
+            self.piece_moves = self.spawn_delay == 0;
+            return;
+        }
+        if !self.piece_moves {
+            return;
+        }
+        self.apply_gravity();
+        if self.test_position(None, None, Some(self.piece.y + 1)) {
+            return;
+        }
+        let lock = &mut self.lock;
+        lock.frames += 1;
+        let out_of_resets =
+            self.config.lock_reset == LockReset::Move && lock.resets >= self.config.max_lock_resets;
+        if lock.frames >= self.config.rules.level(self.level).lock_delay || out_of_resets {
+            self.lock_piece();
+        }
+    }
+
+    // lets the piece fall by the gravity of the level, several rows a frame above 1G
+    fn apply_gravity(&mut self) {
+        let gravity = self.level_rules().gravity;
+        self.gravity_progress += match self.soft_drop {
+            Some(factor) => gravity * factor as f32,
+            None => gravity,
+        };
+        while self.gravity_progress >= 1.0 {
+            self.gravity_progress -= 1.0;
+            let moved = if self.soft_drop.is_some() {
+                self.soft_drop()
+            } else {
+                self.change_piece_position(0, 1)
+            };
+            if !moved {
+                // resting on the stack
+                self.gravity_progress = 0.0;
+            }
+        }
+    }
+
+    // moves the piece one row down on the player's request, worth a point per row
+    pub fn soft_drop(&mut self) -> bool {
+        let moved = self.change_piece_position(0, 1);
+        if moved {
+            self.score += 1;
+        }
+        moved
+    }
+
+    // drops the piece as low as possible and locks it at once, worth two points per row
+    pub fn hard_drop(&mut self) {
+        if !self.piece_moves {
+            return;
+        }
+        let landing_y = self.landing_y();
+        if landing_y > self.piece.y {
+            // like any other move, falling after a rotation is no spin
+            self.last_rotation = None;
+        }
+        self.score += 2 * (landing_y - self.piece.y) as u32;
+        self.piece.y = landing_y;
+        self.lock_piece();
+    }
+
+    fn lock_piece(&mut self) {
+        let lines_before = self.lines_cleared;
+        let top_out = self.fix_piece().top_out;
+        self.check_goal(lines_before);
+        if matches!(self.current_state, Finished | End) {
+            return;
+        }
+        if let Some(reason) = top_out {
+            self.game_over(reason);
+        }
+    }
+
+    // takes the split times and ends the game, or starts the credits roll,
+    // once the goal of the mode is reached
+    fn check_goal(&mut self, lines_before: u32) {
+        if let GameMode::Dig { .. } = self.config.mode {
+            self.feed_garbage();
+            if self.garbage_left() == 0 && !matches!(self.current_state, End) {
+                self.finish();
+            }
+            return;
+        }
+        if let GameMode::Sprint { lines } = self.config.mode {
+            let time = self.time_played();
+            for _ in lines_before / SPLIT_LINES..self.lines_cleared.min(lines) / SPLIT_LINES {
+                self.splits.push(time);
+            }
+        }
+        let Some(goal) = self.config.mode.goal_lines() else {
+            return;
+        };
+        if self.credits_left.is_some() || self.lines_cleared < goal {
+            return;
+        }
+        if let GameMode::Marathon { credits: true, .. } = self.config.mode {
+            // the roll starts on an empty board
+            self.board = Board::new(self.config.cols, self.config.map_rows());
+            self.credits_left = Some(CREDITS_SECONDS * FRAMES_PER_SECOND);
+        } else {
+            self.finish();
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.current_state = Finished;
+        self.piece_moves = false;
+        self.update_time();
+        if let Some(limit) = self.config.mode.time_limit() {
+            self.total_time_played = self.total_time_played.min(limit);
+        }
+    }
+
+    // what a finished game is ranked by: its time in races, its score otherwise
+    pub fn result(&self) -> u128 {
+        if self.config.mode.ranks_by_time() {
+            self.total_time_played
+        } else {
+            self.score as u128
+        }
+    }
+
+    // garbage rows of dig mode still to clear, on the board or not
+    pub fn garbage_left(&self) -> u32 {
+        self.garbage_pending + self.board.count_rows_with(GARBAGE) as u32
+    }
+
+    // pushes in pending dig mode garbage from below: all of it, or up to DIG_FEED_ROWS
+    // rows on the board when feeding
+    fn feed_garbage(&mut self) {
+        let GameMode::Dig {
+            messiness, feed, ..
+        } = self.config.mode
+        else {
+            return;
+        };
+        let on_board = self.board.count_rows_with(GARBAGE) as u32;
+        let room = if feed {
+            DIG_FEED_ROWS.min(self.config.garbage_room())
+        } else {
+            u32::MAX
+        };
+        let rows = room.saturating_sub(on_board).min(self.garbage_pending);
+        self.garbage_pending -= rows;
+        let holes: Vec<usize> = (0..rows).map(|_| self.next_hole(messiness)).collect();
+        self.add_garbage(&holes);
+    }
+
+    // column of the next garbage hole, away from the last one `messiness` percent of the time
+    fn next_hole(&mut self, messiness: u32) -> usize {
+        let cols = self.config.cols;
+        let hole = match self.garbage_hole {
+            // a single column has nowhere else to put it
+            Some(hole) if cols == 1 || self.garbage_rng.gen_range(0..100) >= messiness => hole,
+            Some(hole) => (hole + self.garbage_rng.gen_range(1..cols)) % cols,
+            None => self.garbage_rng.gen_range(0..cols),
+        };
+        self.garbage_hole = Some(hole);
+        hole
+    }
+
+    pub fn game_over(&mut self, reason: TopOut) {
+        if let GameMode::Zen { .. } = self.config.mode {
+            // practice goes on with an empty board and a new start for the piece
+            self.board = Board::new(self.config.cols, self.config.map_rows());
+            self.spawn_piece(self.piece.kind);
+            return;
+        }
+        self.top_out = Some(reason);
+        if self.credits_left.is_some() {
+            // the goal was reached already, topping out only ends the credits roll
+            self.finish();
+            return;
+        }
+        self.current_state = End;
+        self.piece_moves = false;
+        self.update_time();
+    }
+
+    // milliseconds played, the stretch since the last pause included
+    pub fn time_played(&self) -> u128 {
+        let mut el = self.total_time_played;
+        if matches!(self.current_state, Playing) {
+            el += SystemTime::now()
+                .duration_since(self.time_measure_start)
+                .map_or(0, |d| d.as_millis());
+        }
+        el
+    }
+
+    pub fn update_time(&mut self) {
+        let el = SystemTime::now()
+            .duration_since(self.time_measure_start)
+            .unwrap()
+            .as_millis();
+        self.total_time_played += el;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::ULTRA_SECONDS;
+
+    // a game on a 10 column map, 20 rows high with the 20 hidden rows above
+    fn game(mode: GameMode) -> Game {
+        Game::new(GameConfig {
+            cols: 10,
+            mode,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    // fills the bottom rows of the map, `#` for a block, the last string is the bottom row
+    fn fill(game: &mut Game, rows: &[&str]) {
+        let bottom = game.board.height() - rows.len();
+        for (dy, row) in rows.iter().enumerate() {
+            for (x, case) in row.chars().enumerate() {
+                game.board
+                    .set(x, bottom + dy, if case == '#' { 1 } else { 0 });
+            }
+        }
+    }
+
+    // replaces the falling piece
+    fn place(game: &mut Game, kind: PieceKind, x: isize, y: isize, rot: usize) {
+        game.piece = Piece { kind, x, y, rot };
+    }
+
+    // clears the bottom row with a lying I, which leaves the board empty
+    fn clear_line(game: &mut Game) {
+        fill(game, &["....######"]);
+        place(game, PieceKind::I, 0, 0, 0);
+        game.hard_drop();
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_pieces() {
+        let kinds = |seed| {
+            let mut game = Game::new(GameConfig {
+                seed,
+                preview: MAX_PREVIEW,
+                ..Default::default()
+            })
+            .unwrap();
+            let mut kinds = vec![game.piece.kind];
+            for _ in 0..20 {
+                game.next_piece();
+                kinds.push(game.piece.kind);
+            }
+            kinds.extend(game.next_pieces());
+            kinds
+        };
+        assert_eq!(kinds(5), kinds(5));
+        assert_ne!(kinds(5), kinds(6));
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut game = game(GameMode::Endless);
+        fill(
+            &mut game,
+            &["..#.......", "##...#####", "###.######", "#########."],
+        );
+        place(&mut game, PieceKind::T, 2, 36, 1);
+        assert!(game.rotate_piece(Rotation::Clockwise));
+        game.hard_drop();
+        let lock = game.last_lock.unwrap();
+        assert_eq!(
+            lock.clear,
+            Clear {
+                lines: 2,
+                spin: Spin::Full,
+                perfect: false
+            }
+        );
+        assert_eq!(lock.score.clear, 1200);
+    }
+
+    #[test]
+    fn t_spin_mini() {
+        let mut game = game(GameMode::Endless);
+        fill(&mut game, &["#.........", "...#######", "#.#######."]);
+        place(&mut game, PieceKind::T, 0, 37, 1);
+        assert!(game.rotate_piece(Rotation::CounterClockwise));
+        assert_eq!((game.piece.x, game.piece.y), (0, 37));
+        game.hard_drop();
+        let lock = game.last_lock.unwrap();
+        assert_eq!((lock.clear.lines, lock.clear.spin), (1, Spin::Mini));
+        assert_eq!(lock.score.clear, 200);
+    }
+
+    #[test]
+    fn hard_drop_after_rotating_in_the_air_is_not_a_spin() {
+        let mut game = game(GameMode::Endless);
+        fill(&mut game, &["#.........", "#.........", "#.########"]);
+        place(&mut game, PieceKind::T, 0, 10, 0);
+        assert!(game.rotate_piece(Rotation::Clockwise));
+        game.hard_drop();
+        let lock = game.last_lock.unwrap();
+        assert_eq!((lock.clear.lines, lock.clear.spin), (1, Spin::None));
+    }
+
+    #[test]
+    fn t_spin_without_rotation_is_not_a_spin() {
+        let mut game = game(GameMode::Endless);
+        fill(&mut game, &["#.........", "...#######", "#.#######."]);
+        place(&mut game, PieceKind::T, 0, 30, 0);
+        game.hard_drop();
+        assert_eq!(game.last_lock.unwrap().clear.spin, Spin::None);
+    }
+
+    // T slot three rows deep on columns 3 and 4, the T comes down pointing up
+    // and only the last kick of the counterclockwise turn gets it in
+    fn triple_slot(bottom: &str) -> Game {
+        let mut game = game(GameMode::Endless);
+        fill(
+            &mut game,
+            &[
+                "....#.....",
+                "..........",
+                "####.#####",
+                "###..#####",
+                bottom,
+            ],
+        );
+        place(&mut game, PieceKind::T, 2, 35, 0);
+        assert!(game.rotate_piece(Rotation::CounterClockwise));
+        assert_eq!((game.piece.x, game.piece.y, game.piece.rot), (3, 37, 3));
+        game
+    }
+
+    #[test]
+    fn t_spin_triple() {
+        let mut game = triple_slot("####.#####");
+        game.hard_drop();
+        let lock = game.last_lock.unwrap();
+        assert_eq!((lock.clear.lines, lock.clear.spin), (3, Spin::Full));
+        assert_eq!(lock.score.clear, 1600);
+    }
+
+    #[test]
+    fn last_kick_makes_a_full_spin_without_the_front_corners() {
+        // the bottom left corner is open, so only the kick makes it a full T-spin
+        let mut game = triple_slot("###..#####");
+        game.hard_drop();
+        let lock = game.last_lock.unwrap();
+        assert_eq!((lock.clear.lines, lock.clear.spin), (2, Spin::Full));
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut game = game(GameMode::Endless);
+        clear_line(&mut game);
+        let lock = game.last_lock.unwrap();
+        assert!(lock.clear.perfect);
+        assert_eq!(lock.score.perfect_clear, 800);
+        assert!(game.is_board_empty());
+
+        // a clear leaving blocks behind is not one
+        fill(&mut game, &["#.........", "....######"]);
+        place(&mut game, PieceKind::I, 0, 0, 0);
+        game.hard_drop();
+        assert!(!game.last_lock.unwrap().clear.perfect);
+    }
+
+    #[test]
+    fn block_out_when_the_next_piece_does_not_fit() {
+        let mut game = game(GameMode::Endless);
+        for y in 16..22 {
+            for x in 3..7 {
+                game.board.set(x, y, 1);
+            }
+        }
+        place(&mut game, PieceKind::O, 0, 30, 0);
+        game.hard_drop();
+        assert_eq!(game.top_out, Some(TopOut::BlockOut));
+        assert!(matches!(game.current_state, End));
+        assert!(!game.piece_moves);
+    }
+
+    #[test]
+    fn lock_out_when_a_piece_locks_in_the_hidden_rows() {
+        let mut game = game(GameMode::Endless);
+        game.board.set(0, 2, 1);
+        place(&mut game, PieceKind::I, 0, 0, 0);
+        game.hard_drop();
+        assert_eq!(game.top_out, Some(TopOut::LockOut));
+        assert!(matches!(game.current_state, End));
+    }
+
+    #[test]
+    fn lock_out_can_be_turned_off() {
+        let mut game = Game::new(GameConfig {
+            cols: 10,
+            top_out: TopOutRules {
+                lock_out: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        game.board.set(0, 2, 1);
+        place(&mut game, PieceKind::I, 0, 0, 0);
+        game.hard_drop();
+        assert_eq!(game.top_out, None);
+    }
+
+    #[test]
+    fn garbage_out_when_garbage_pushes_blocks_off_the_top() {
+        let mut game = game(GameMode::Endless);
+        game.board.set(0, 0, 1);
+        game.add_garbage(&[5]);
+        assert_eq!(game.top_out, Some(TopOut::GarbageOut));
+        assert!(matches!(game.current_state, End));
+    }
+
+    #[test]
+    fn zen_empties_the_board_instead_of_ending() {
+        let mut game = game(GameMode::Zen { level: 5 });
+        assert_eq!(game.level, 5);
+        game.board.set(0, 0, 1);
+        game.add_garbage(&[5]);
+        assert_eq!(game.top_out, None);
+        assert!(game.is_board_empty());
+    }
+
+    #[test]
+    fn zen_respawns_the_piece_stuck_above_the_map() {
+        let mut game = Game::new(GameConfig {
+            cols: 10,
+            buffer_rows: 0,
+            top_out: TopOutRules {
+                block_out: false,
+                ..Default::default()
+            },
+            mode: GameMode::Zen { level: 1 },
+            ..Default::default()
+        })
+        .unwrap();
+        for y in 0..4 {
+            for x in 0..9 {
+                game.board.set(x, y, 1);
+            }
+        }
+        // the next piece climbs over the stack, out of the map
+        game.next_piece();
+        assert!(game.piece.y < 0);
+        game.hard_drop();
+        assert!(game.is_board_empty());
+        assert!(game.piece.y >= 0);
+        assert!(game.piece_moves);
+        for _ in 0..2000 {
+            game.update();
+        }
+        assert_eq!(game.top_out, None);
+    }
+
+    // drops the piece onto the floor of an empty map
+    fn ground(game: &mut Game) {
+        place(game, PieceKind::O, 4, 30, 0);
+        game.lock = LockState {
+            lowest_y: 30,
+            ..Default::default()
+        };
+        while game.change_piece_position(0, 1) {}
+    }
+
+    #[test]
+    fn lock_delay_runs_out() {
+        let mut game = game(GameMode::Endless);
+        ground(&mut game);
+        let delay = game.level_rules().lock_delay;
+        for _ in 1..delay {
+            game.update();
+        }
+        assert!(game.last_lock.is_none());
+        game.update();
+        assert!(game.last_lock.is_some());
+    }
+
+    #[test]
+    fn moves_reset_the_lock_delay_up_to_the_cap() {
+        let mut game = Game::new(GameConfig {
+            cols: 10,
+            max_lock_resets: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        ground(&mut game);
+        let delay = game.level_rules().lock_delay;
+        for dx in [-1, 1, -1] {
+            for _ in 1..delay {
+                game.update();
+            }
+            assert!(game.change_piece_position(dx, 0));
+        }
+        assert!(game.last_lock.is_none());
+        assert_eq!(game.lock.resets, 3);
+        // out of resets, it locks on the next grounded frame
+        game.update();
+        assert!(game.last_lock.is_some());
+    }
+
+    #[test]
+    fn step_reset_ignores_moves_on_the_ground() {
+        let mut game = Game::new(GameConfig {
+            cols: 10,
+            lock_reset: LockReset::Step,
+            ..Default::default()
+        })
+        .unwrap();
+        ground(&mut game);
+        let delay = game.level_rules().lock_delay;
+        for _ in 1..delay {
+            game.update();
+            game.change_piece_position(if game.piece.x > 4 { -1 } else { 1 }, 0);
+        }
+        assert!(game.last_lock.is_none());
+        game.update();
+        assert!(game.last_lock.is_some());
+    }
+
+    #[test]
+    fn levels_up_when_the_line_count_reaches_the_threshold() {
+        let mut game = game(GameMode::Endless);
+        for _ in 0..game.level_rules().lines - 1 {
+            clear_line(&mut game);
+        }
+        assert_eq!(game.level, 1);
+        clear_line(&mut game);
+        assert_eq!(game.level, 2);
+    }
+
+    #[test]
+    fn sprint_finishes_at_its_goal_with_split_times() {
+        let mut game = game(GameMode::Sprint { lines: 20 });
+        for _ in 0..19 {
+            clear_line(&mut game);
+        }
+        assert!(matches!(game.current_state, Start));
+        assert_eq!(game.splits.len(), 1);
+        clear_line(&mut game);
+        assert!(matches!(game.current_state, Finished));
+        assert_eq!(game.splits.len(), 2);
+        assert!(!game.piece_moves);
+        assert_eq!(game.result(), game.total_time_played);
+    }
+
+    #[test]
+    fn ultra_finishes_on_its_time_limit() {
+        let mut game = game(GameMode::Ultra {
+            seconds: ULTRA_SECONDS,
+        });
+        let limit = ULTRA_SECONDS as u128 * 1000;
+        game.total_time_played = limit - 1;
+        game.update();
+        assert!(matches!(game.current_state, Start));
+        game.total_time_played = limit + 500;
+        game.update();
+        assert!(matches!(game.current_state, Finished));
+        assert_eq!(game.total_time_played, limit);
+        game.score = 1234;
+        assert_eq!(game.result(), 1234);
+    }
+
+    #[test]
+    fn marathon_finishes_at_its_goal() {
+        let mut game = game(GameMode::Marathon {
+            lines: 3,
+            credits: false,
+        });
+        for _ in 0..2 {
+            clear_line(&mut game);
+        }
+        assert!(matches!(game.current_state, Start));
+        clear_line(&mut game);
+        assert!(matches!(game.current_state, Finished));
+    }
+
+    #[test]
+    fn marathon_credits_roll_plays_out_after_the_goal() {
+        let mut game = game(GameMode::Marathon {
+            lines: 1,
+            credits: true,
+        });
+        game.board.set(9, 30, 1);
+        fill(&mut game, &["....######"]);
+        place(&mut game, PieceKind::I, 0, 0, 0);
+        game.hard_drop();
+        // the roll starts on an empty board
+        assert!(game.is_board_empty());
+        let frames = CREDITS_SECONDS * FRAMES_PER_SECOND;
+        assert_eq!(game.credits_left, Some(frames));
+        for _ in 0..frames {
+            game.update();
+        }
+        assert!(!matches!(game.current_state, Finished));
+        game.update();
+        assert!(matches!(game.current_state, Finished));
+    }
+
+    #[test]
+    fn topping_out_in_the_credits_roll_still_finishes() {
+        let mut game = game(GameMode::Marathon {
+            lines: 1,
+            credits: true,
+        });
+        clear_line(&mut game);
+        game.game_over(TopOut::BlockOut);
+        assert!(matches!(game.current_state, Finished));
+        assert_eq!(game.top_out, Some(TopOut::BlockOut));
+    }
+
+    #[test]
+    fn dig_finishes_when_the_garbage_is_cleared() {
+        let mut game = game(GameMode::Dig {
+            rows: 3,
+            messiness: 0,
+            feed: false,
+        });
+        assert_eq!(game.garbage_left(), 3);
+        // without messiness the holes line up, a standing I clears them all
+        let hole = game.garbage_hole.unwrap() as isize;
+        for y in 37..40 {
+            assert_eq!(game.board.get(hole as usize, y), 0);
+        }
+        place(&mut game, PieceKind::I, hole - 2, 0, 1);
+        game.hard_drop();
+        assert_eq!(game.garbage_left(), 0);
+        assert!(matches!(game.current_state, Finished));
+        assert_eq!(game.result(), game.total_time_played);
+    }
+
+    #[test]
+    fn dig_feeds_garbage_from_below() {
+        let mut game = game(GameMode::Dig {
+            rows: 15,
+            messiness: 100,
+            feed: true,
+        });
+        assert_eq!(game.board.count_rows_with(GARBAGE), DIG_FEED_ROWS as usize);
+        assert_eq!(game.garbage_left(), 15);
+        // full messiness never puts a hole under the one above
+        let holes: Vec<usize> = (30..40)
+            .map(|y| (0..10).find(|&x| game.board.get(x, y) == 0).unwrap())
+            .collect();
+        assert!(holes.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // clearing the top row brings the next one up
+        let hole = holes[0] as isize;
+        place(&mut game, PieceKind::I, hole - 2, 0, 1);
+        game.hard_drop();
+        assert_eq!(game.garbage_left(), 14);
+        assert_eq!(
+            game.board.count_rows_with(GARBAGE) as u32,
+            DIG_FEED_ROWS.min(game.garbage_left())
+        );
+    }
+
+    #[test]
+    fn rejects_maps_the_board_cannot_hold() {
+        assert!(Game::with_size(70, 20).is_err());
+        assert!(Game::with_size(0, 20).is_err());
+        assert!(Game::with_size(10, 0).is_err());
+        assert!(Game::with_size(MAX_COLS, 20).is_ok());
+        assert!(Game::with_size(PIECEWIDTH, PIECEWIDTH).is_ok());
+    }
+
+    #[test]
+    fn rejects_dig_goals_the_map_cannot_hold() {
+        let dig = |rows, feed| GameConfig {
+            rows: 20,
+            mode: GameMode::Dig {
+                rows,
+                messiness: 100,
+                feed,
+            },
+            ..Default::default()
+        };
+        assert!(Game::new(dig(18, false)).is_ok());
+        assert!(Game::new(dig(19, false)).is_err());
+        assert!(Game::new(dig(100, true)).is_ok());
+    }
+}
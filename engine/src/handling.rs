@@ -0,0 +1,122 @@
+use crate::game::Game;
+
+// how held keys repeat, in frames
+#[derive(Clone, Copy, Debug)]
+pub struct Handling {
+    // delayed auto shift: frames a direction is held before it starts repeating
+    pub das: u32,
+    // auto repeat rate: frames between repeated shifts, 0 moves straight to the wall
+    pub arr: u32,
+    // soft drop factor: gravity multiplier while soft drop is held
+    pub sdf: u32,
+}
+
+impl Default for Handling {
+    fn default() -> Handling {
+        Handling {
+            das: 10,
+            arr: 2,
+            sdf: 20,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Left,
+    Right,
+    SoftDrop,
+}
+
+// held/released state of the movement keys, independent of the falling piece
+// so the DAS charge carries over to the next one
+pub struct InputState {
+    pub handling: Handling,
+    left: bool,
+    right: bool,
+    soft_drop: bool,
+    // -1, 1, or 0 when no direction is held; the last one pressed wins
+    direction: isize,
+    das_frames: u32,
+    arr_frames: u32,
+}
+
+impl InputState {
+    pub fn new(handling: Handling) -> InputState {
+        InputState {
+            handling,
+            left: false,
+            right: false,
+            soft_drop: false,
+            direction: 0,
+            das_frames: 0,
+            arr_frames: 0,
+        }
+    }
+
+    // a key went down: the piece moves once right away
+    pub fn press(&mut self, input: Input, game: &mut Game) {
+        match input {
+            Input::Left => self.left = true,
+            Input::Right => self.right = true,
+            Input::SoftDrop => {
+                self.soft_drop = true;
+                game.soft_drop();
+                return;
+            }
+        }
+        self.charge(if input == Input::Left { -1 } else { 1 });
+        game.change_piece_position(self.direction, 0);
+    }
+
+    // a key went up: falls back to the other direction if it is still held
+    pub fn release(&mut self, input: Input) {
+        match input {
+            Input::Left => self.left = false,
+            Input::Right => self.right = false,
+            Input::SoftDrop => {
+                self.soft_drop = false;
+                return;
+            }
+        }
+        match (self.left, self.right) {
+            (true, false) => self.charge(-1),
+            (false, true) => self.charge(1),
+            _ => self.direction = 0,
+        }
+    }
+
+    fn charge(&mut self, direction: isize) {
+        self.direction = direction;
+        self.das_frames = 0;
+        self.arr_frames = 0;
+    }
+
+    // advances one frame: passes the soft drop factor on to the game, charges DAS,
+    // then repeats the shift every ARR frames
+    pub fn update(&mut self, game: &mut Game) {
+        game.soft_drop = self.soft_drop.then_some(self.handling.sdf.max(1));
+
+        if self.direction == 0 {
+            return;
+        }
+        if self.das_frames < self.handling.das {
+            self.das_frames += 1;
+            if self.das_frames < self.handling.das {
+                return;
+            }
+        } else {
+            self.arr_frames += 1;
+            if self.arr_frames < self.handling.arr {
+                return;
+            }
+        }
+        self.arr_frames = 0;
+
+        if self.handling.arr == 0 {
+            while game.change_piece_position(self.direction, 0) {}
+        } else {
+            game.change_piece_position(self.direction, 0);
+        }
+    }
+}
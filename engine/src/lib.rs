@@ -0,0 +1,22 @@
+pub mod board;
+pub mod game;
+pub mod handling;
+pub mod modes;
+pub mod pieces;
+pub mod randomizer;
+pub mod rotation;
+pub mod rules;
+pub mod scoring;
+
+pub use board::Board;
+pub use game::{
+    Game, GameConfig, GameState, LockReset, TopOut, TopOutRules, BUFFER_ROWS, FRAMES_PER_SECOND,
+    GAMEMAP_COLS, GAMEMAP_ROWS, GARBAGE, MAX_PREVIEW,
+};
+pub use handling::{Handling, Input, InputState};
+pub use modes::GameMode;
+pub use pieces::{Piece, PieceKind};
+pub use randomizer::{Randomizer, RandomizerKind};
+pub use rotation::{Rotation, RotationSystem, RotationSystemKind};
+pub use rules::{Level, Rules};
+pub use scoring::{Clear, LockResult, ScoreBreakdown, Scoring, Spin};
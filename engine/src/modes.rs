@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+pub const SPRINT_LINES: u32 = 40;
+pub const ULTRA_SECONDS: u32 = 120;
+pub const MARATHON_LINES: u32 = 150;
+// length of the bonus round played after a marathon
+pub const CREDITS_SECONDS: u32 = 60;
+pub const DIG_ROWS: u32 = 10;
+// chance in percent that the hole of a garbage row is not under the one above
+pub const DIG_MESSINESS: u32 = 100;
+// garbage rows kept on the board while dig mode feeds them
+pub const DIG_FEED_ROWS: u32 = 10;
+// a split time is taken every this many lines
+pub const SPLIT_LINES: u32 = 10;
+
+// what the player is playing for, and what ends the game besides topping out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GameMode {
+    // plays until topping out
+    #[default]
+    Endless,
+    // clears `lines` as fast as possible
+    Sprint {
+        lines: u32,
+    },
+    // scores as much as possible in `seconds`
+    Ultra {
+        seconds: u32,
+    },
+    // clears `lines` for the best score, then plays the credits roll if `credits` is set
+    Marathon {
+        lines: u32,
+        credits: bool,
+    },
+    // practice at the gravity of `level`, topping out only empties the board
+    Zen {
+        level: u32,
+    },
+    // clears `rows` rows of garbage as fast as possible, all there at the start,
+    // or fed from below a few at a time if `feed` is set
+    Dig {
+        rows: u32,
+        messiness: u32,
+        feed: bool,
+    },
+}
+
+impl GameMode {
+    // sets the target of modes that have one
+    pub fn with_goal(self, goal: u32) -> GameMode {
+        match self {
+            GameMode::Endless => GameMode::Endless,
+            GameMode::Sprint { .. } => GameMode::Sprint { lines: goal },
+            GameMode::Ultra { .. } => GameMode::Ultra { seconds: goal },
+            GameMode::Marathon { credits, .. } => GameMode::Marathon {
+                lines: goal,
+                credits,
+            },
+            GameMode::Zen { level } => GameMode::Zen { level },
+            GameMode::Dig {
+                messiness, feed, ..
+            } => GameMode::Dig {
+                rows: goal,
+                messiness,
+                feed,
+            },
+        }
+    }
+
+    // lines that end the game
+    pub fn goal_lines(&self) -> Option<u32> {
+        match self {
+            GameMode::Sprint { lines } | GameMode::Marathon { lines, .. } => Some(*lines),
+            _ => None,
+        }
+    }
+
+    // play time after which the game is over, in milliseconds
+    pub fn time_limit(&self) -> Option<u128> {
+        match self {
+            GameMode::Ultra { seconds } => Some(*seconds as u128 * 1000),
+            _ => None,
+        }
+    }
+
+    // races are ranked by time, the other modes by score
+    pub fn ranks_by_time(&self) -> bool {
+        matches!(self, GameMode::Sprint { .. } | GameMode::Dig { .. })
+    }
+
+    // true if `result` ranks above `best`
+    pub fn beats(&self, result: u128, best: u128) -> bool {
+        if self.ranks_by_time() {
+            result < best
+        } else {
+            result > best
+        }
+    }
+
+    // short name, also used to tell personal bests apart
+    pub fn name(&self) -> String {
+        match self {
+            GameMode::Endless => "endless".to_string(),
+            GameMode::Sprint { lines } => format!("sprint{}", lines),
+            GameMode::Ultra { seconds } => format!("ultra{}", seconds),
+            GameMode::Marathon { lines, credits } => {
+                format!("marathon{}{}", lines, if *credits { "credits" } else { "" })
+            }
+            GameMode::Zen { .. } => "zen".to_string(),
+            GameMode::Dig {
+                rows,
+                messiness,
+                feed,
+            } => format!(
+                "dig{}m{}{}",
+                rows,
+                messiness,
+                if *feed { "feed" } else { "" }
+            ),
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GameMode, String> {
+        match s {
+            "endless" => Ok(GameMode::Endless),
+            "sprint" => Ok(GameMode::Sprint {
+                lines: SPRINT_LINES,
+            }),
+            "ultra" => Ok(GameMode::Ultra {
+                seconds: ULTRA_SECONDS,
+            }),
+            "marathon" => Ok(GameMode::Marathon {
+                lines: MARATHON_LINES,
+                credits: false,
+            }),
+            "zen" => Ok(GameMode::Zen { level: 1 }),
+            "dig" => Ok(GameMode::Dig {
+                rows: DIG_ROWS,
+                messiness: DIG_MESSINESS,
+                feed: false,
+            }),
+            _ => Err(format!("unknown game mode: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn races_rank_by_time_the_rest_by_score() {
+        let sprint: GameMode = "sprint".parse().unwrap();
+        assert!(sprint.beats(50_000, 60_000));
+        let ultra: GameMode = "ultra".parse().unwrap();
+        assert!(ultra.beats(60_000, 50_000));
+        assert!(!ultra.beats(50_000, 50_000));
+    }
+
+    #[test]
+    fn credits_runs_keep_their_own_best() {
+        let marathon = |credits| GameMode::Marathon {
+            lines: MARATHON_LINES,
+            credits,
+        };
+        assert_eq!(marathon(false).name(), "marathon150");
+        assert_eq!(marathon(true).name(), "marathon150credits");
+    }
+
+    #[test]
+    fn names_tell_apart_every_setting_of_a_best() {
+        let dig: GameMode = "dig".parse().unwrap();
+        assert_eq!(dig.name(), "dig10m100");
+        let fed = GameMode::Dig {
+            rows: 100,
+            messiness: 40,
+            feed: true,
+        };
+        assert_eq!(fed.name(), "dig100m40feed");
+        assert_eq!(
+            "sprint".parse::<GameMode>().unwrap().with_goal(20).name(),
+            "sprint20"
+        );
+        assert!("tetris".parse::<GameMode>().is_err());
+    }
+}
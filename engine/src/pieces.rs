@@ -0,0 +1,217 @@
+// the seven tetrominoes, in the order of their colour codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    I,
+    L,
+    J,
+    O,
+    S,
+    Z,
+    T,
+}
+
+pub const PIECE_KINDS: [PieceKind; 7] = [
+    PieceKind::I,
+    PieceKind::L,
+    PieceKind::J,
+    PieceKind::O,
+    PieceKind::S,
+    PieceKind::Z,
+    PieceKind::T,
+];
+pub const PIECEWIDTH: usize = 4;
+
+// blocks of one orientation as (x, y) in the bounding box
+type Cells = [(u8, u8); 4];
+
+// what never changes about a piece kind
+struct PieceData {
+    // SRS orientations: spawn state, then each clockwise turn
+    cells: [Cells; 4],
+    // the same orientations as one bitmask per row, bit i = column i
+    rows: [[u8; PIECEWIDTH]; 4],
+    // side of the square box the piece turns in
+    size: u8,
+}
+
+const fn piece_data(cells: [Cells; 4], size: u8) -> PieceData {
+    let mut rows = [[0; PIECEWIDTH]; 4];
+    let mut rot = 0;
+    while rot < 4 {
+        let mut i = 0;
+        while i < 4 {
+            let (x, y) = cells[rot][i];
+            rows[rot][y as usize] |= 1 << x;
+            i += 1;
+        }
+        rot += 1;
+    }
+    PieceData { cells, rows, size }
+}
+
+static PIECES: [PieceData; 7] = [
+    piece_data(
+        [
+            [(0, 1), (1, 1), (2, 1), (3, 1)],
+            [(2, 0), (2, 1), (2, 2), (2, 3)],
+            [(0, 2), (1, 2), (2, 2), (3, 2)],
+            [(1, 0), (1, 1), (1, 2), (1, 3)],
+        ],
+        4,
+    ),
+    piece_data(
+        [
+            [(2, 0), (0, 1), (1, 1), (2, 1)],
+            [(1, 0), (1, 1), (1, 2), (2, 2)],
+            [(0, 1), (1, 1), (2, 1), (0, 2)],
+            [(0, 0), (1, 0), (1, 1), (1, 2)],
+        ],
+        3,
+    ),
+    piece_data(
+        [
+            [(0, 0), (0, 1), (1, 1), (2, 1)],
+            [(1, 0), (2, 0), (1, 1), (1, 2)],
+            [(0, 1), (1, 1), (2, 1), (2, 2)],
+            [(1, 0), (1, 1), (0, 2), (1, 2)],
+        ],
+        3,
+    ),
+    piece_data([[(0, 0), (1, 0), (0, 1), (1, 1)]; 4], 2),
+    piece_data(
+        [
+            [(1, 0), (2, 0), (0, 1), (1, 1)],
+            [(1, 0), (1, 1), (2, 1), (2, 2)],
+            [(1, 1), (2, 1), (0, 2), (1, 2)],
+            [(0, 0), (0, 1), (1, 1), (1, 2)],
+        ],
+        3,
+    ),
+    piece_data(
+        [
+            [(0, 0), (1, 0), (1, 1), (2, 1)],
+            [(2, 0), (1, 1), (2, 1), (1, 2)],
+            [(0, 1), (1, 1), (1, 2), (2, 2)],
+            [(1, 0), (0, 1), (1, 1), (0, 2)],
+        ],
+        3,
+    ),
+    piece_data(
+        [
+            [(1, 0), (0, 1), (1, 1), (2, 1)],
+            [(1, 0), (1, 1), (2, 1), (1, 2)],
+            [(0, 1), (1, 1), (2, 1), (1, 2)],
+            [(1, 0), (0, 1), (1, 1), (1, 2)],
+        ],
+        3,
+    ),
+];
+
+impl PieceKind {
+    fn data(self) -> &'static PieceData {
+        &PIECES[self as usize]
+    }
+
+    // colour code of its blocks on the board, from 1
+    pub fn code(self) -> u8 {
+        self as u8 + 1
+    }
+
+    // blocks of orientation `rot` within the bounding box
+    pub fn cells(self, rot: usize) -> &'static Cells {
+        &self.data().cells[rot]
+    }
+
+    // orientation `rot` as one bitmask per row
+    pub fn shape(self, rot: usize) -> &'static [u8; PIECEWIDTH] {
+        &self.data().rows[rot]
+    }
+
+    pub fn size(self) -> usize {
+        self.data().size as usize
+    }
+
+    // cell the piece turns around; I and O turn around its bottom right corner instead
+    pub fn pivot(self) -> (isize, isize) {
+        let center = (self.size() as isize - 1) / 2;
+        (center, center)
+    }
+}
+
+// a piece on the board: top left corner of its bounding box and orientation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub x: isize,
+    pub y: isize,
+    pub rot: usize,
+}
+
+impl Piece {
+    pub fn new(kind: PieceKind) -> Piece {
+        Piece {
+            kind,
+            x: 0,
+            y: 0,
+            rot: 0,
+        }
+    }
+
+    // new piece in orientation `rot` on a map `cols` wide, centered, rounding to the left,
+    // resting on the lowest of the `buffer_rows` hidden rows, or as low as the top of the map allows
+    pub fn spawn(kind: PieceKind, rot: usize, cols: usize, buffer_rows: usize) -> Piece {
+        // cells are listed top to bottom
+        let cells = kind.cells(rot);
+        let (top, bottom) = (cells[0].1, cells[3].1);
+        Piece {
+            x: cols.saturating_sub(kind.size()) as isize / 2,
+            y: (buffer_rows as isize - 1 - bottom as isize).max(-(top as isize)),
+            rot,
+            ..Piece::new(kind)
+        }
+    }
+
+    // current orientation as one bitmask per row
+    pub fn shape(&self) -> &'static [u8; PIECEWIDTH] {
+        self.kind.shape(self.rot)
+    }
+
+    // board positions of its blocks
+    pub fn cells(&self) -> impl Iterator<Item = (isize, isize)> {
+        let (px, py) = (self.x, self.y);
+        self.kind
+            .cells(self.rot)
+            .iter()
+            .map(move |&(x, y)| (px + x as isize, py + y as isize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_match_the_cells() {
+        for kind in PIECE_KINDS {
+            for rot in 0..4 {
+                let mut shape = [0; PIECEWIDTH];
+                for &(x, y) in kind.cells(rot) {
+                    shape[y as usize] |= 1 << x;
+                }
+                assert_eq!(&shape, kind.shape(rot), "{:?} rot {}", kind, rot);
+            }
+        }
+    }
+
+    #[test]
+    fn spawns_centered_on_the_lowest_hidden_row() {
+        let t = Piece::spawn(PieceKind::T, 0, 10, 20);
+        assert_eq!((t.x, t.y), (3, 18));
+        assert_eq!(t.cells().map(|(_, y)| y).max(), Some(19));
+        let i = Piece::spawn(PieceKind::I, 0, 10, 20);
+        assert_eq!((i.x, i.y), (3, 18));
+        // without hidden rows the top of the piece is on the first row
+        let o = Piece::spawn(PieceKind::O, 0, 10, 0);
+        assert_eq!(o.cells().map(|(_, y)| y).min(), Some(0));
+    }
+}
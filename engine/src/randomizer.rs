@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::pieces::{PieceKind, PIECE_KINDS};
+
+const NOPIECETYPES: usize = PIECE_KINDS.len();
+
+// source of the piece sequence, the same seed always gives the same sequence
+pub trait Randomizer {
+    fn next(&mut self) -> PieceKind;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RandomizerKind {
+    Uniform,
+    #[default]
+    Bag7,
+    Bag14,
+    History,
+    Nes,
+}
+
+impl RandomizerKind {
+    pub fn build(self, seed: u64) -> Box<dyn Randomizer> {
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+        match self {
+            RandomizerKind::Uniform => Box::new(Uniform { rng }),
+            RandomizerKind::Bag7 => Box::new(Bag::new(rng, 1)),
+            RandomizerKind::Bag14 => Box::new(Bag::new(rng, 2)),
+            RandomizerKind::History => Box::new(History::new(rng)),
+            RandomizerKind::Nes => Box::new(Nes {
+                rng,
+                prev: NOPIECETYPES,
+            }),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RandomizerKind, String> {
+        match s {
+            "uniform" => Ok(RandomizerKind::Uniform),
+            "bag7" => Ok(RandomizerKind::Bag7),
+            "bag14" => Ok(RandomizerKind::Bag14),
+            "history" => Ok(RandomizerKind::History),
+            "nes" => Ok(RandomizerKind::Nes),
+            _ => Err(format!("unknown randomizer: {}", s)),
+        }
+    }
+}
+
+// every piece type with the same probability, no memory
+pub struct Uniform {
+    rng: ChaCha8Rng,
+}
+
+impl Randomizer for Uniform {
+    fn next(&mut self) -> PieceKind {
+        PIECE_KINDS[self.rng.gen_range(0..NOPIECETYPES)]
+    }
+}
+
+// shuffled bags holding `copies` of each piece type, dealt one by one
+pub struct Bag {
+    rng: ChaCha8Rng,
+    copies: usize,
+    bag: Vec<PieceKind>,
+}
+
+impl Bag {
+    fn new(rng: ChaCha8Rng, copies: usize) -> Bag {
+        Bag {
+            rng,
+            copies,
+            bag: Vec::with_capacity(NOPIECETYPES * copies),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self) -> PieceKind {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(PIECE_KINDS);
+            }
+            self.bag.shuffle(&mut self.rng);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+// TGM style: rerolls a few times while the piece is among the last 4 dealt
+pub struct History {
+    rng: ChaCha8Rng,
+    history: [PieceKind; 4],
+    first: bool,
+}
+
+const HISTORY_REROLLS: usize = 6;
+
+impl History {
+    fn new(rng: ChaCha8Rng) -> History {
+        History {
+            rng,
+            history: [PieceKind::Z, PieceKind::S, PieceKind::Z, PieceKind::S],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for History {
+    fn next(&mut self) -> PieceKind {
+        let pt = if self.first {
+            // the first piece is never an S, Z or O
+            self.first = false;
+            *[PieceKind::I, PieceKind::L, PieceKind::J, PieceKind::T]
+                .choose(&mut self.rng)
+                .unwrap()
+        } else {
+            let mut pt = PIECE_KINDS[self.rng.gen_range(0..NOPIECETYPES)];
+            for _ in 0..HISTORY_REROLLS {
+                if !self.history.contains(&pt) {
+                    break;
+                }
+                pt = PIECE_KINDS[self.rng.gen_range(0..NOPIECETYPES)];
+            }
+            pt
+        };
+        self.history.rotate_right(1);
+        self.history[0] = pt;
+        pt
+    }
+}
+
+// NES algorithm: roll 8 sides, reroll once on a repeat or on the dummy side
+pub struct Nes {
+    rng: ChaCha8Rng,
+    prev: usize,
+}
+
+impl Randomizer for Nes {
+    fn next(&mut self) -> PieceKind {
+        let mut rand_nb = self.rng.gen_range(0..=NOPIECETYPES);
+        if rand_nb == NOPIECETYPES || rand_nb == self.prev {
+            rand_nb = self.rng.gen_range(0..NOPIECETYPES);
+        }
+        self.prev = rand_nb;
+        PIECE_KINDS[rand_nb]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [RandomizerKind; 5] = [
+        RandomizerKind::Uniform,
+        RandomizerKind::Bag7,
+        RandomizerKind::Bag14,
+        RandomizerKind::History,
+        RandomizerKind::Nes,
+    ];
+
+    fn sequence(kind: RandomizerKind, seed: u64, len: usize) -> Vec<PieceKind> {
+        let mut randomizer = kind.build(seed);
+        (0..len).map(|_| randomizer.next()).collect()
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_sequence() {
+        for kind in KINDS {
+            assert_eq!(
+                sequence(kind, 42, 100),
+                sequence(kind, 42, 100),
+                "{:?}",
+                kind
+            );
+            assert_ne!(
+                sequence(kind, 42, 100),
+                sequence(kind, 43, 100),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn bags_deal_every_piece_once_per_bag() {
+        for (kind, copies) in [(RandomizerKind::Bag7, 1), (RandomizerKind::Bag14, 2)] {
+            let size = NOPIECETYPES * copies;
+            for bag in sequence(kind, 7, size * 10).chunks(size) {
+                for piece in PIECE_KINDS {
+                    let count = bag.iter().filter(|&&p| p == piece).count();
+                    assert_eq!(count, copies, "{:?} in {:?}", piece, kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn history_never_starts_with_s_z_or_o() {
+        for seed in 0..50 {
+            let first = sequence(RandomizerKind::History, seed, 1)[0];
+            assert!(![PieceKind::S, PieceKind::Z, PieceKind::O].contains(&first));
+        }
+    }
+}
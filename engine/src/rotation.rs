@@ -0,0 +1,327 @@
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::pieces::{Piece, PieceKind};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+impl Rotation {
+    // orientation reached when turning from `rot`
+    pub fn apply(self, rot: usize) -> usize {
+        match self {
+            Rotation::Clockwise => (rot + 1) % 4,
+            Rotation::CounterClockwise => (rot + 3) % 4,
+            Rotation::Half => (rot + 2) % 4,
+        }
+    }
+}
+
+// decides where a piece may end up when it turns; orientations are the SRS ones of `PieceKind`,
+// systems that place them differently in the box move them with `offset`
+pub trait RotationSystem {
+    // orientation pieces of `kind` spawn in
+    fn spawn_state(&self, _kind: PieceKind) -> usize {
+        0
+    }
+
+    // where orientation `rot` sits compared to the SRS one, turning moves the piece by the
+    // difference between the two states
+    fn offset(&self, _kind: PieceKind, _rot: usize) -> (isize, isize) {
+        (0, 0)
+    }
+
+    // offsets (dx, dy) tried in order when turning a piece from state `from` in direction `dir`,
+    // the first one that fits wins; y points down like the game map rows
+    fn kicks(&self, kind: PieceKind, from: usize, dir: Rotation) -> &'static [(isize, isize)];
+
+    // false if `turned`, the piece in its new orientation before any kick, must not be kicked
+    // on `board` when it does not fit
+    fn may_kick(&self, _turned: &Piece, _board: &Board) -> bool {
+        true
+    }
+
+    // true if a T-spin whose rotation needed kick number `kick` is a full one whatever the
+    // corners in front of the T
+    fn is_full_spin_kick(
+        &self,
+        _kind: PieceKind,
+        _from: usize,
+        _dir: Rotation,
+        _kick: usize,
+    ) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RotationSystemKind {
+    #[default]
+    Srs,
+    Ars,
+}
+
+impl RotationSystemKind {
+    pub fn build(self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs),
+            RotationSystemKind::Ars => Box::new(Ars),
+        }
+    }
+}
+
+impl FromStr for RotationSystemKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RotationSystemKind, String> {
+        match s {
+            "srs" => Ok(RotationSystemKind::Srs),
+            "ars" => Ok(RotationSystemKind::Ars),
+            _ => Err(format!("unknown rotation system: {}", s)),
+        }
+    }
+}
+
+const NO_KICK: [(isize, isize); 1] = [(0, 0)];
+
+// Super Rotation System tables, indexed by the starting state;
+// the vertical component is negated compared to the usual y-up tables
+const JLSTZ_CW: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+];
+const JLSTZ_CCW: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+];
+const I_CW: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+];
+const I_CCW: [[(isize, isize); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+    [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+];
+
+// SRS has no 180 turns, this is the common extension shared by all pieces
+const HALF: [[(isize, isize); 6]; 4] = [
+    [(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)],
+    [(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)],
+];
+
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn kicks(&self, kind: PieceKind, from: usize, dir: Rotation) -> &'static [(isize, isize)] {
+        match (kind, dir) {
+            (PieceKind::O, _) => &NO_KICK,
+            (_, Rotation::Half) => &HALF[from],
+            (PieceKind::I, Rotation::Clockwise) => &I_CW[from],
+            (PieceKind::I, Rotation::CounterClockwise) => &I_CCW[from],
+            (_, Rotation::Clockwise) => &JLSTZ_CW[from],
+            (_, Rotation::CounterClockwise) => &JLSTZ_CCW[from],
+        }
+    }
+
+    // the last quarter turn kick is the one behind T-spin triples and fins
+    fn is_full_spin_kick(
+        &self,
+        _kind: PieceKind,
+        _from: usize,
+        dir: Rotation,
+        kick: usize,
+    ) -> bool {
+        dir != Rotation::Half && kick == 4
+    }
+}
+
+// Arika Rotation System: pieces spawn flat side up and sit at the bottom of their box,
+// S, Z and I keep to the same two columns when standing; the kicks are one column to the
+// right, then to the left, and the I piece does not kick
+const ARS_KICKS: [(isize, isize); 3] = [(0, 0), (1, 0), (-1, 0)];
+
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn spawn_state(&self, kind: PieceKind) -> usize {
+        match kind {
+            PieceKind::I | PieceKind::O => 0,
+            _ => 2,
+        }
+    }
+
+    fn offset(&self, kind: PieceKind, rot: usize) -> (isize, isize) {
+        match (kind, rot) {
+            (PieceKind::I, 2) => (0, -1),
+            (PieceKind::I, 3) => (1, 0),
+            (PieceKind::S, 1) => (-1, 0),
+            (PieceKind::Z, 3) => (1, 0),
+            (PieceKind::L | PieceKind::J | PieceKind::T | PieceKind::S | PieceKind::Z, 0) => (0, 1),
+            _ => (0, 0),
+        }
+    }
+
+    fn kicks(&self, kind: PieceKind, _from: usize, _dir: Rotation) -> &'static [(isize, isize)] {
+        match kind {
+            PieceKind::I | PieceKind::O => &NO_KICK,
+            _ => &ARS_KICKS,
+        }
+    }
+
+    // centre column rule: L, J and T do not kick when the first of their blocked cells,
+    // reading the box row by row, is in the middle column
+    fn may_kick(&self, turned: &Piece, board: &Board) -> bool {
+        if !matches!(turned.kind, PieceKind::L | PieceKind::J | PieceKind::T) {
+            return true;
+        }
+        let mut cells: Vec<(isize, isize)> = turned.cells().collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        match cells.iter().find(|&&(x, y)| board.is_occupied(x, y)) {
+            Some(&(x, _)) => x - turned.x != 1,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameConfig};
+
+    // a 10 column game with `piece` in place of the spawned one
+    fn game_with(rotation: RotationSystemKind, piece: Piece) -> Game {
+        let mut game = Game::new(GameConfig {
+            cols: 10,
+            rotation,
+            ..Default::default()
+        })
+        .unwrap();
+        game.piece = piece;
+        game
+    }
+
+    fn srs_game(kind: PieceKind, x: isize, y: isize, rot: usize) -> Game {
+        game_with(RotationSystemKind::Srs, Piece { kind, x, y, rot })
+    }
+
+    fn ars_game(kind: PieceKind) -> Game {
+        let rot = Ars.spawn_state(kind);
+        game_with(
+            RotationSystemKind::Ars,
+            Piece {
+                kind,
+                x: 3,
+                y: 30,
+                rot,
+            },
+        )
+    }
+
+    fn sorted_cells(game: &Game) -> Vec<(isize, isize)> {
+        let mut cells: Vec<(isize, isize)> = game.piece.cells().collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn srs_kicks_off_the_walls() {
+        // standing I on the right wall, lying down needs the -1 column kick
+        let mut game = srs_game(PieceKind::I, 7, 30, 1);
+        assert!(game.rotate_piece(Rotation::Clockwise));
+        assert_eq!((game.piece.x, game.piece.y, game.piece.rot), (6, 30, 2));
+
+        // T pointing right on the left wall turns up one column further right
+        let mut game = srs_game(PieceKind::T, -1, 30, 1);
+        assert!(game.rotate_piece(Rotation::CounterClockwise));
+        assert_eq!((game.piece.x, game.piece.y, game.piece.rot), (0, 30, 0));
+    }
+
+    #[test]
+    fn srs_kicks_off_the_floor() {
+        // I lying on the floor stands up two rows higher with the last kick
+        let mut game = srs_game(PieceKind::I, 3, 38, 0);
+        assert!(game.rotate_piece(Rotation::Clockwise));
+        assert_eq!((game.piece.x, game.piece.y, game.piece.rot), (4, 36, 1));
+        assert_eq!(game.piece.cells().map(|(_, y)| y).max(), Some(39));
+    }
+
+    #[test]
+    fn srs_fails_when_no_kick_fits() {
+        let mut game = srs_game(PieceKind::I, 3, 38, 0);
+        for y in 30..38 {
+            for x in 0..10 {
+                game.board.set(x, y, 1);
+            }
+        }
+        assert!(!game.rotate_piece(Rotation::Clockwise));
+        assert_eq!((game.piece.x, game.piece.y, game.piece.rot), (3, 38, 0));
+    }
+
+    #[test]
+    fn only_the_last_srs_quarter_turn_kick_makes_full_spins() {
+        for dir in [Rotation::Clockwise, Rotation::CounterClockwise] {
+            for kick in 0..5 {
+                assert_eq!(Srs.is_full_spin_kick(PieceKind::T, 0, dir, kick), kick == 4);
+            }
+        }
+        assert!(!Srs.is_full_spin_kick(PieceKind::T, 0, Rotation::Half, 4));
+        assert!(!Ars.is_full_spin_kick(PieceKind::T, 0, Rotation::Clockwise, 2));
+    }
+
+    #[test]
+    fn ars_two_state_pieces_keep_their_place() {
+        for kind in [PieceKind::I, PieceKind::S, PieceKind::Z] {
+            let mut game = ars_game(kind);
+            let mut states = Vec::new();
+            for _ in 0..4 {
+                states.push(sorted_cells(&game));
+                assert!(game.rotate_piece(Rotation::Clockwise));
+            }
+            assert_eq!(states[0], states[2], "{:?} lying", kind);
+            assert_eq!(states[1], states[3], "{:?} standing", kind);
+            assert_eq!(states[0], sorted_cells(&game), "{:?} back", kind);
+        }
+    }
+
+    #[test]
+    fn ars_lying_states_sit_at_the_bottom_of_the_box() {
+        for kind in [PieceKind::L, PieceKind::J, PieceKind::T] {
+            let mut game = ars_game(kind);
+            let bottom = |game: &Game| game.piece.cells().map(|(_, y)| y).max();
+            let spawned = bottom(&game);
+            // spawns flat side up, the point of T and the foot of L and J hang below
+            assert_eq!(game.piece.cells().filter(|&(_, y)| y == 31).count(), 3);
+            game.rotate_piece(Rotation::Clockwise);
+            game.rotate_piece(Rotation::Clockwise);
+            assert_eq!(bottom(&game), spawned, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn ars_kicks_unless_blocked_in_the_centre_column() {
+        // L lies on rows 31 and 32 and stands up on columns 3 and 4 from row 30
+        let mut game = ars_game(PieceKind::L);
+        game.board.set(3, 30, 1);
+        assert!(game.rotate_piece(Rotation::Clockwise));
+        assert_eq!(game.piece.x, 4);
+
+        let mut game = ars_game(PieceKind::L);
+        game.board.set(4, 32, 1);
+        assert!(!game.rotate_piece(Rotation::Clockwise));
+        assert_eq!(game.piece.rot, Ars.spawn_state(PieceKind::L));
+    }
+}
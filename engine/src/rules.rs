@@ -0,0 +1,96 @@
+use std::fs;
+
+use serde::Deserialize;
+
+// timings of one level, delays in frames
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Level {
+    // rows the piece falls per frame: 1/64 is about a row a second, 20 drops it to the stack at once
+    pub gravity: f32,
+    pub lock_delay: u32,
+    // pause after a lock that cleared lines, on top of the entry delay
+    pub line_clear_delay: u32,
+    // pause between a lock and the next piece appearing
+    pub entry_delay: u32,
+    // total lines cleared to reach the next level, it is reached as soon as the count gets there
+    pub lines: u32,
+}
+
+// difficulty curve, one entry per level starting at level 1
+#[derive(Clone, Debug, Deserialize)]
+pub struct Rules {
+    pub levels: Vec<Level>,
+}
+
+// the built-in curve, read from the file players copy to make their own
+const DEFAULT_RULES: &str = include_str!("../../rules/default.toml");
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::from_toml(DEFAULT_RULES).expect("rules/default.toml is valid")
+    }
+}
+
+impl Rules {
+    pub fn from_toml(content: &str) -> Result<Rules, String> {
+        let rules: Rules = toml::from_str(content).map_err(|e| e.to_string())?;
+        if rules.levels.is_empty() {
+            return Err("rules need at least one level".to_string());
+        }
+        Ok(rules)
+    }
+
+    pub fn load(file_name: &str) -> Result<Rules, String> {
+        let content = fs::read_to_string(file_name).map_err(|e| format!("{}: {}", file_name, e))?;
+        Rules::from_toml(&content).map_err(|e| format!("{}: {}", file_name, e))
+    }
+
+    // timings of `level`, counted from 1; levels past the end of the curve keep the last one
+    pub fn level(&self, level: u32) -> &Level {
+        let index = (level as usize).clamp(1, self.levels.len()) - 1;
+        &self.levels[index]
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.levels.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_curve_holds_the_old_speed_and_line_tables() {
+        // rows fell every LEVEL_TIMES milliseconds before the rules file
+        const LEVEL_TIMES: [u32; 14] = [
+            700, 600, 500, 400, 300, 250, 220, 200, 190, 180, 170, 160, 150, 140,
+        ];
+        const LEVEL_LINES: [u32; 14] =
+            [10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 120, 140, 150, 160];
+        let rules = Rules::default();
+        assert_eq!(rules.max_level(), 14);
+        for (level, (&time, &lines)) in rules
+            .levels
+            .iter()
+            .zip(LEVEL_TIMES.iter().zip(&LEVEL_LINES))
+        {
+            let frames_per_row = 1.0 / level.gravity;
+            assert!((frames_per_row - time as f32 * 60.0 / 1000.0).abs() < 0.01);
+            assert_eq!(level.lines, lines);
+            assert_eq!(level.lock_delay, 30);
+        }
+    }
+
+    #[test]
+    fn levels_past_the_curve_keep_the_last_one() {
+        let rules = Rules::default();
+        assert_eq!(rules.level(0).lines, 10);
+        assert_eq!(rules.level(99).lines, 160);
+    }
+
+    #[test]
+    fn rejects_empty_curves() {
+        assert!(Rules::from_toml("levels = []").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// guideline scoring: what each lock achieved, and what it is worth
+
+use crate::game::TopOut;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+// lines cleared by one lock and how the piece got there
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Clear {
+    pub lines: u32,
+    pub spin: Spin,
+    // nothing left on the board afterwards
+    pub perfect: bool,
+}
+
+impl Clear {
+    // guideline base value, before the level multiplier
+    pub fn base_points(&self) -> u32 {
+        match (self.spin, self.lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, _) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        }
+    }
+
+    // tetrises and spins clearing lines keep a back-to-back chain going
+    pub fn is_difficult(&self) -> bool {
+        self.lines > 0 && (self.lines >= 4 || self.spin != Spin::None)
+    }
+
+    // text shown when the lock is announced, empty when there is nothing to show
+    pub fn name(&self) -> String {
+        let lines = match self.lines {
+            0 => "",
+            1 => "Single",
+            2 => "Double",
+            3 => "Triple",
+            _ => "Tetris",
+        };
+        let spin = match self.spin {
+            Spin::None => "",
+            Spin::Mini => "T-Spin Mini",
+            Spin::Full => "T-Spin",
+        };
+        [spin, lines]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// points awarded for one lock, by source
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ScoreBreakdown {
+    pub clear: u32,
+    pub back_to_back: u32,
+    pub combo: u32,
+    pub perfect_clear: u32,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> u32 {
+        self.clear + self.back_to_back + self.combo + self.perfect_clear
+    }
+}
+
+// state carried from one lock to the next
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Scoring {
+    // difficult clears in a row, the bonus applies from the second one
+    pub back_to_back: u32,
+    // consecutive locks clearing lines, minus one
+    pub combo: Option<u32>,
+}
+
+const COMBO_POINTS: u32 = 50;
+
+impl Scoring {
+    // updates the chains with a new lock and returns what it is worth at `level`
+    pub fn score_lock(&mut self, clear: Clear, level: u32) -> ScoreBreakdown {
+        let mut breakdown = ScoreBreakdown {
+            clear: clear.base_points() * level,
+            ..Default::default()
+        };
+
+        if clear.lines == 0 {
+            // locks without lines end the combo but keep back-to-back alive
+            self.combo = None;
+            return breakdown;
+        }
+
+        if clear.is_difficult() {
+            self.back_to_back += 1;
+            if self.back_to_back > 1 {
+                breakdown.back_to_back = breakdown.clear / 2;
+            }
+        } else {
+            self.back_to_back = 0;
+        }
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        self.combo = Some(combo);
+        breakdown.combo = COMBO_POINTS * combo * level;
+
+        if clear.perfect {
+            let bonus = match clear.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if self.back_to_back > 1 => 3200,
+                _ => 2000,
+            };
+            breakdown.perfect_clear = bonus * level;
+        }
+
+        breakdown
+    }
+}
+
+// what happened when the piece locked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct LockResult {
+    pub clear: Clear,
+    pub score: ScoreBreakdown,
+    // set if the lock ended the game
+    pub top_out: Option<TopOut>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u32, spin: Spin) -> Clear {
+        Clear {
+            lines,
+            spin,
+            perfect: false,
+        }
+    }
+
+    #[test]
+    fn back_to_back_pays_from_the_second_difficult_clear() {
+        let mut scoring = Scoring::default();
+        let tetris = clear(4, Spin::None);
+        assert_eq!(scoring.score_lock(tetris, 1).back_to_back, 0);
+        // a lock without lines keeps the chain
+        scoring.score_lock(clear(0, Spin::None), 1);
+        let tsd = scoring.score_lock(clear(2, Spin::Full), 2);
+        assert_eq!(tsd.clear, 2400);
+        assert_eq!(tsd.back_to_back, 1200);
+        // a single breaks it
+        scoring.score_lock(clear(1, Spin::None), 1);
+        assert_eq!(scoring.score_lock(tetris, 1).back_to_back, 0);
+    }
+
+    #[test]
+    fn combo_grows_with_each_clearing_lock() {
+        let mut scoring = Scoring::default();
+        let single = clear(1, Spin::None);
+        let combos: Vec<u32> = (0..4)
+            .map(|_| scoring.score_lock(single, 3).combo)
+            .collect();
+        assert_eq!(combos, [0, 150, 300, 450]);
+        scoring.score_lock(clear(0, Spin::None), 3);
+        assert_eq!(scoring.score_lock(single, 3).combo, 0);
+    }
+
+    #[test]
+    fn perfect_clear_bonus_depends_on_the_lines() {
+        let perfect = |lines| Clear {
+            perfect: true,
+            ..clear(lines, Spin::None)
+        };
+        let bonus = |lines| {
+            Scoring::default()
+                .score_lock(perfect(lines), 1)
+                .perfect_clear
+        };
+        assert_eq!([1, 2, 3, 4].map(bonus), [800, 1200, 1800, 2000]);
+
+        let mut scoring = Scoring::default();
+        scoring.score_lock(clear(4, Spin::None), 1);
+        assert_eq!(scoring.score_lock(perfect(4), 1).perfect_clear, 3200);
+    }
+
+    #[test]
+    fn names_spins_and_clears() {
+        assert_eq!(clear(3, Spin::Full).name(), "T-Spin Triple");
+        assert_eq!(clear(0, Spin::Mini).name(), "T-Spin Mini");
+        assert_eq!(clear(4, Spin::None).name(), "Tetris");
+        assert_eq!(clear(0, Spin::None).name(), "");
+    }
+}
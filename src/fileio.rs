@@ -61,3 +61,20 @@ pub fn load_highscores_and_lines() -> Option<(Vec<u32>, Vec<u32>)> {
         panic!("cant read from scores.txt file");
     }
 }
+
+// best result of a game mode, kept in its own file
+pub fn load_personal_best(mode: &str) -> Option<u128> {
+    read_from_file(&personal_best_file(mode))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub fn save_personal_best(mode: &str, best: u128) -> io::Result<()> {
+    write_into_file(format!("{}\n", best), &personal_best_file(mode))
+}
+
+fn personal_best_file(mode: &str) -> String {
+    format!("pb_{}.txt", mode)
+}
@@ -1,13 +1,11 @@
-use std::time::SystemTime;
-
-use crate::{
-    game::{
-        self, Game,
-        GameState::{self, Playing},
-        GAMEMAP_COLS, GAMEMAP_ROWS, LEVEL_TIMES, PIECE_SIZE, WIN_HEIGHT, WIN_MARGIN, WIN_WIDTH,
-    },
-    helpers::ColorFromU32,
-    Result,
+use std::time::{Duration, SystemTime};
+
+use crate::{helpers::ColorFromU32, Result};
+
+use engine::{
+    game::{self, Game, FRAMES_PER_SECOND, GAMEMAP_COLS, GAMEMAP_ROWS},
+    modes::{GameMode, SPLIT_LINES},
+    pieces::{PieceKind, PIECEWIDTH},
 };
 
 use sdl2::{
@@ -20,14 +18,47 @@ use sdl2::{
     EventPump,
 };
 
+pub const WIN_WIDTH: u32 = 600;
+pub const WIN_HEIGHT: u32 = 800;
+pub const WIN_MARGIN: u32 = 4;
+pub const PIECE_SIZE: u32 = 32;
+pub const PREVIEW_SIZE: u32 = 14;
+// room kept above and below the well
+pub const WELL_MARGIN_Y: u32 = 80;
+
+// minutes, seconds and milliseconds
+fn format_time(millis: u128) -> String {
+    let secs = millis / 1000;
+    format!("{}:{:0>2}.{:0>3}", secs / 60, secs % 60, millis % 1000)
+}
+
+// room in pixels left to the well in a window of the given size
+fn well_area(width: u32, height: u32) -> (u32, u32) {
+    let side_panel = PREVIEW_SIZE * PIECEWIDTH as u32 + WIN_MARGIN * 3;
+    (
+        width.saturating_sub(side_panel * 2),
+        height.saturating_sub(WELL_MARGIN_Y * 2),
+    )
+}
+
+// largest map the window shows, with tiles of a single pixel
+pub fn max_board_size() -> (usize, usize) {
+    let (w, h) = well_area(WIN_WIDTH, WIN_HEIGHT);
+    (w as usize, h as usize)
+}
+
 pub struct GameWindow<'a> {
     pub canvas: Canvas<Window>,
     pub tc: Option<&'a TextureCreator<WindowContext>>,
     pub event_pump: EventPump,
-    pub step_timer: SystemTime,
-    pub global_timer: SystemTime,
+    // start of the next game frame not yet run
+    pub frame_timer: SystemTime,
     pub width: u32,
     pub height: u32,
+    // size of the map in tiles, and of a tile in pixels so the map fits the window
+    pub cols: usize,
+    pub rows: usize,
+    pub tile_size: u32,
 }
 
 impl<'a> GameWindow<'a> {
@@ -47,20 +78,33 @@ impl<'a> GameWindow<'a> {
         Ok(GameWindow {
             canvas,
             event_pump: sdl_context.event_pump()?,
-            step_timer: SystemTime::now(),
-            global_timer: SystemTime::now(),
+            frame_timer: SystemTime::now(),
             width: WIN_WIDTH,
             height: WIN_HEIGHT,
+            cols: GAMEMAP_COLS,
+            rows: GAMEMAP_ROWS,
+            tile_size: PIECE_SIZE,
             tc: None,
         })
     }
 
+    // scales the tiles so a map of the given size fits between the side panels,
+    // never larger than PIECE_SIZE
+    pub fn fit_board(&mut self, cols: usize, rows: usize) {
+        let (max_w, max_h) = well_area(self.width, self.height);
+        self.cols = cols;
+        self.rows = rows;
+        self.tile_size = (max_w / cols as u32)
+            .min(max_h / rows as u32)
+            .clamp(1, PIECE_SIZE);
+    }
+
     // creates sdl texture
     pub fn create_tex(&mut self, col: Color) -> Result<Texture<'a>> {
-        let mut tex =
-            self.tc
-                .unwrap()
-                .create_texture_target(None, PIECE_SIZE as u32, PIECE_SIZE as u32)?;
+        let mut tex = self
+            .tc
+            .unwrap()
+            .create_texture_target(None, PIECE_SIZE, PIECE_SIZE)?;
 
         self.canvas.with_texture_canvas(&mut tex, |texture| {
             texture.set_draw_color(col);
@@ -88,12 +132,7 @@ impl<'a> GameWindow<'a> {
             self.canvas.clear();
             BKG_COLOR_R += BKG_COLOR_STEP;
         }
-        let (x, y, w, h) = (
-            ((self.width - PIECE_SIZE * GAMEMAP_COLS as u32) / 2) as i32,
-            ((self.height - PIECE_SIZE * GAMEMAP_ROWS as u32) / 2) as i32,
-            PIECE_SIZE * GAMEMAP_COLS as u32,
-            PIECE_SIZE * GAMEMAP_ROWS as u32,
-        );
+        let (x, y, w, h) = self.well_rect();
 
         self.draw_rect(
             x - WIN_MARGIN as i32,
@@ -107,22 +146,93 @@ impl<'a> GameWindow<'a> {
 
         Ok(())
     }
+    // position and size of the well, centered in the window
+    pub fn well_rect(&self) -> (i32, i32, u32, u32) {
+        let (w, h) = (
+            self.tile_size * self.cols as u32,
+            self.tile_size * self.rows as u32,
+        );
+        (
+            (self.width.saturating_sub(w) / 2) as i32,
+            (self.height.saturating_sub(h) / 2) as i32,
+            w,
+            h,
+        )
+    }
+    // draws a piece type in its spawn orientation with small tiles, its top row at `y`
+    fn draw_small_piece(
+        &mut self,
+        game: &Game,
+        kind: PieceKind,
+        x: i32,
+        y: i32,
+        color_palette: &[Texture],
+    ) -> Result<()> {
+        // cells are listed top to bottom
+        let cells = kind.cells(game.spawn_state(kind));
+        let top = cells[0].1;
+        for &(cell_x, cell_y) in cells {
+            self.draw_rect(
+                x + (cell_x as u32 * PREVIEW_SIZE) as i32,
+                y + ((cell_y - top) as u32 * PREVIEW_SIZE) as i32,
+                PREVIEW_SIZE,
+                PREVIEW_SIZE,
+                &color_palette[kind.code() as usize - 1],
+            )?;
+        }
+        Ok(())
+    }
+    // draws the upcoming pieces to the right of the well
+    pub fn draw_next_pieces(&mut self, game: &Game, color_palette: &[Texture]) -> Result<()> {
+        let (well_x, mut y, well_w, _) = self.well_rect();
+        let x = well_x + (well_w + WIN_MARGIN * 2) as i32;
+
+        for kind in game.next_pieces() {
+            self.draw_small_piece(game, kind, x, y, color_palette)?;
+            y += (PREVIEW_SIZE * 3) as i32;
+        }
+        Ok(())
+    }
+    // draws the hold slot to the left of the well
+    pub fn draw_held_piece(&mut self, game: &Game, color_palette: &[Texture]) -> Result<()> {
+        if !game.config.hold {
+            return Ok(());
+        }
+        let (well_x, _, _, _) = self.well_rect();
+        let (x, y) = (
+            well_x - (WIN_MARGIN * 2 + PREVIEW_SIZE * PIECEWIDTH as u32) as i32,
+            (self.height / 4) as i32,
+        );
+        let frame = self.create_tex(Color::RGB(0, 0, 0))?;
+        self.draw_rect(
+            x - WIN_MARGIN as i32,
+            y - WIN_MARGIN as i32,
+            PREVIEW_SIZE * PIECEWIDTH as u32 + WIN_MARGIN * 2,
+            PREVIEW_SIZE * 2 + WIN_MARGIN * 2,
+            &frame,
+        )?;
+        if let Some(kind) = game.held {
+            self.draw_small_piece(game, kind, x, y, color_palette)?;
+        }
+        Ok(())
+    }
     pub fn draw_rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: &Texture) -> Result<()> {
         self.canvas.copy(color, None, Rect::new(x, y, w, h))?;
         Ok(())
     }
     pub fn draw_tile(&mut self, x: i32, y: i32, color: &Texture) -> Result<()> {
-        self.draw_rect(x, y, PIECE_SIZE as u32, PIECE_SIZE as u32, color)?;
+        self.draw_rect(x, y, self.tile_size, self.tile_size, color)?;
         Ok(())
     }
-    pub fn timer_tick(&self, level: u32) -> bool {
-        match self.step_timer.elapsed() {
-            Ok(elapsed) => {
-                let millis = elapsed.as_secs() as u32 * 1000 + elapsed.subsec_millis();
-                millis > LEVEL_TIMES[level as usize - 1]
-            }
-            Err(_) => false,
+    // number of whole game frames elapsed since the previous call
+    pub fn frames_due(&mut self) -> u32 {
+        let frame = Duration::from_secs(1) / FRAMES_PER_SECOND;
+        let mut frames = 0;
+        while self.frame_timer.elapsed().is_ok_and(|el| el >= frame) {
+            self.frame_timer += frame;
+            frames += 1;
         }
+        frames
     }
 
     pub fn display_text_line(
@@ -173,32 +283,160 @@ impl<'a> GameWindow<'a> {
         self.display_text_line(font, col!(2), format!("Level: {}", game.level), 10, y)?;
         y += h;
 
-        let mut el = game.total_time_played;
-        if matches!(game.current_state, Playing) {
-            el += SystemTime::now()
-                .duration_since(game.time_measure_start)?
-                .as_millis();
-        }
-
-        let millis = el % 1000;
-        el /= 1000;
-        let (secs, mins) = (el % 60, el / 60);
-        let elapsed_text = format!("Time: {}:{:0>2}.{:0>3}", mins, secs, millis);
+        // timed modes count down
+        let elapsed_text = match game.config.mode.time_limit() {
+            Some(limit) => format!(
+                "Time left: {}",
+                format_time(limit.saturating_sub(game.time_played()))
+            ),
+            None => format!("Time: {}", format_time(game.time_played())),
+        };
 
         self.display_text_line(font, col!(3), elapsed_text, 10, y)?;
+        y += h;
+
+        self.display_text_line(font, col!(4), format!("Seed: {}", game.config.seed), 10, y)?;
+        y += h;
+
+        if let GameMode::Sprint { lines } = game.config.mode {
+            let text = format!("Sprint: {}/{}", game.lines_cleared.min(lines), lines);
+            self.display_text_line(font, col!(1), text, 10, y)?;
+            y += h;
+            // the last few splits
+            let first_shown = game.splits.len().saturating_sub(4);
+            for (i, &split) in game.splits.iter().enumerate().skip(first_shown) {
+                let text = format!(
+                    "{} lines: {}",
+                    (i as u32 + 1) * SPLIT_LINES,
+                    format_time(split)
+                );
+                self.display_text_line(font, col!(3), text, 10, y)?;
+                y += h;
+            }
+        }
+        if let GameMode::Marathon { lines, .. } = game.config.mode {
+            let text = match game.credits_left {
+                Some(frames) => format!("Credits: {}s", frames.div_ceil(FRAMES_PER_SECOND)),
+                None => format!("Marathon: {}/{}", game.lines_cleared.min(lines), lines),
+            };
+            self.display_text_line(font, col!(1), text, 10, y)?;
+            y += h;
+        }
+        if let GameMode::Zen { .. } = game.config.mode {
+            self.display_text_line(font, col!(1), "Zen".to_string(), 10, y)?;
+            y += h;
+        }
+        if let GameMode::Dig { .. } = game.config.mode {
+            let text = format!("Garbage left: {}", game.garbage_left());
+            self.display_text_line(font, col!(1), text, 10, y)?;
+            y += h;
+        }
+        if let Some(best) = game.personal_best {
+            let text = if game.config.mode.ranks_by_time() {
+                format!("Best: {}", format_time(best))
+            } else {
+                format!("Best: {}", best)
+            };
+            self.display_text_line(font, col!(0), text, 10, y)?;
+            y += h;
+        }
 
+        // points of the last lock, by source
+        if let Some(lock) = game.last_lock {
+            let name = lock.clear.name();
+            if !name.is_empty() {
+                let text = format!("{} +{}", name, lock.score.clear);
+                self.display_text_line(font, col!(5), text, 10, y)?;
+                y += h;
+            }
+            if lock.score.back_to_back > 0 {
+                let text = format!(
+                    "Back-to-back x{} +{}",
+                    game.scoring.back_to_back - 1,
+                    lock.score.back_to_back
+                );
+                self.display_text_line(font, col!(6), text, 10, y)?;
+                y += h;
+            }
+            if lock.score.combo > 0 {
+                let text = format!(
+                    "Combo {} +{}",
+                    game.scoring.combo.unwrap_or_default(),
+                    lock.score.combo
+                );
+                self.display_text_line(font, col!(7), text, 10, y)?;
+                y += h;
+            }
+            if lock.score.perfect_clear > 0 {
+                let text = format!("Perfect clear +{}", lock.score.perfect_clear);
+                self.display_text_line(font, col!(0), text, 10, y)?;
+            }
+        }
+
+        Ok(())
+    }
+    // announces a perfect clear over the well until the next piece locks
+    pub fn display_announcement(&mut self, game: &Game, font: &Font) -> Result<()> {
+        if game.last_lock.is_some_and(|lock| lock.clear.perfect) {
+            let text = "PERFECT CLEAR";
+            let (w, _) = font.size_of(text)?;
+            let (_, well_y, _, well_h) = self.well_rect();
+            self.display_text_line(
+                font,
+                &Color::RGB(255, 255, 0),
+                text.to_string(),
+                (self.width - w) / 2,
+                (well_y + well_h as i32 / 3) as u32,
+            )?;
+        }
         Ok(())
     }
-    pub fn display_state_info(&mut self, state: &GameState, font: &Font) -> Result<()> {
-        match state {
+    pub fn display_state_info(&mut self, game: &Game, font: &Font) -> Result<()> {
+        match game.current_state {
             game::GameState::End => {
-                self.display_text_line(
-                    font,
-                    &Color::RGB(255, 0, 0),
-                    "GAME OVER".to_string(),
-                    self.width / 2,
-                    self.height / 2,
-                )?;
+                let h = self
+                    .display_text_line(
+                        font,
+                        &Color::RGB(255, 0, 0),
+                        "GAME OVER".to_string(),
+                        self.width / 2,
+                        self.height / 2,
+                    )?
+                    .height();
+                if let Some(top_out) = game.top_out {
+                    self.display_text_line(
+                        font,
+                        &Color::RGB(255, 0, 0),
+                        top_out.reason().to_uppercase(),
+                        self.width / 2,
+                        self.height / 2 + h,
+                    )?;
+                }
+            }
+            game::GameState::Finished => {
+                let (x, y) = (self.width / 2, self.height / 2);
+                let red = Color::RGB(255, 0, 0);
+                let h = self
+                    .display_text_line(font, &red, "FINISHED".to_string(), x, y)?
+                    .height();
+                let (mode, result) = (game.config.mode, game.result());
+                let result_text = if mode.ranks_by_time() {
+                    format_time(result)
+                } else {
+                    format!("SCORE {}", result)
+                };
+                self.display_text_line(font, &red, result_text, x, y + h)?;
+                let comparison = match (mode, game.personal_best) {
+                    (_, Some(best)) if !mode.beats(result, best) => {
+                        if mode.ranks_by_time() {
+                            format!("BEST +{}", format_time(result - best))
+                        } else {
+                            format!("BEST {}", best)
+                        }
+                    }
+                    _ => "NEW PERSONAL BEST".to_string(),
+                };
+                self.display_text_line(font, &red, comparison, x, y + h * 2)?;
             }
             game::GameState::Start => {
                 self.display_text_line(
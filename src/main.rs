@@ -8,29 +8,33 @@ use sdl2::{
     event::Event,
     keyboard::Keycode,
     pixels::Color,
-    render::{Texture, TextureCreator},
+    render::{BlendMode, Texture, TextureCreator},
     ttf::FontStyle,
     video::WindowContext,
 };
 
 use std::time::{Duration, SystemTime};
 
-use game::{
-    Game,
+use engine::{
+    pieces, Game,
     GameState::{self, Playing},
-    GAMEMAP_COLS, GAMEMAP_ROWS, PIECE_SIZE,
+    Input, InputState, Rotation,
 };
 use gamewindow::GameWindow;
 use helpers::Result;
+use settings::Settings;
 
 mod fileio;
-mod game;
 mod gamewindow;
 mod helpers;
-mod pieces;
+mod settings;
+
+const GHOST_ALPHA: u8 = 64;
 
 pub fn main() -> Result<()> {
-    let mut game = Game::new();
+    let mut settings = Settings::from_args()?;
+    let mut game = settings.new_game()?;
+    let mut input_state = InputState::new(settings.handling);
     let mut gw = GameWindow::new()?;
     let texture_creator: TextureCreator<WindowContext> = gw.canvas.texture_creator(); // texture creator must be created in
     gw.tc = Some(&texture_creator);
@@ -42,8 +46,7 @@ pub fn main() -> Result<()> {
 
     font.set_style(FontStyle::BOLD);
 
-    let grid_x = ((gw.width - PIECE_SIZE as u32 * GAMEMAP_COLS as u32) / 2) as i32;
-    let grid_y = ((gw.height - PIECE_SIZE as u32 * GAMEMAP_ROWS as u32) / 2) as i32;
+    gw.fit_board(settings.config.cols, settings.config.rows);
 
     let color_palettes: [[u32; 8]; 3] = [
         // various color palettes to chose from
@@ -65,47 +68,101 @@ pub fn main() -> Result<()> {
         .iter()
         .map(|&val| gw.create_tex(Color::fromu32(val)).unwrap())
         .collect();
+    // same colors, see-through, for the ghost piece
+    let ghost_palette: Vec<_> = color_palettes[1]
+        .iter()
+        .map(|&val| {
+            let mut tex = gw.create_tex(Color::fromu32(val)).unwrap();
+            tex.set_blend_mode(BlendMode::Blend);
+            tex.set_alpha_mod(GHOST_ALPHA);
+            tex
+        })
+        .collect();
     let mut should_quit;
 
     'main_loop: loop {
+        let (grid_x, grid_y, _, _) = gw.well_rect();
         gw.draw_background()?;
         gw.display_game_information(&game, &font, color_palettes[2])?;
         draw_other_pieces(&game, &mut gw, grid_x, grid_y, &texture_palette)?;
-        gw.display_state_info(&game.current_state, &font)?;
-        (should_quit) = handle_events(&mut game, &mut gw);
-        if game.piece.moves {
-            draw_current_piece(&game.piece, &mut gw, grid_x, grid_y, &texture_palette)?;
+        gw.draw_next_pieces(&game, &texture_palette)?;
+        gw.draw_held_piece(&game, &texture_palette)?;
+        gw.display_announcement(&game, &font)?;
+        gw.display_state_info(&game, &font)?;
+        (should_quit) = handle_events(&mut game, &mut gw, &mut settings, &mut input_state)?;
+        if game.piece_moves {
+            let buffer_rows = game.config.buffer_rows as isize;
+            if settings.ghost {
+                let landing_y = game.landing_y() - buffer_rows;
+                draw_current_piece(
+                    &game.piece,
+                    landing_y,
+                    &mut gw,
+                    grid_x,
+                    grid_y,
+                    &ghost_palette,
+                )?;
+            }
+            draw_current_piece(
+                &game.piece,
+                game.piece.y - buffer_rows,
+                &mut gw,
+                grid_x,
+                grid_y,
+                &texture_palette,
+            )?;
         }
 
-        if matches!(game.current_state, GameState::Playing)
-            && gw.timer_tick(game.level)
-            && matches!(game.current_state, Playing)
-        {
-            if !game.change_piece_position(0, 1) && !game.fix_piece() {
-                // game over
-                game.current_state = GameState::End;
-                game.update_time();
-                // gw.print_game_over();
+        for _ in 0..gw.frames_due() {
+            if matches!(game.current_state, Playing) {
+                input_state.update(&mut game);
+                game.update();
             }
-            gw.step_timer = SystemTime::now();
         }
         gw.canvas.present();
 
         if should_quit {
-            game.print_game_info();
+            print_game_info(&game);
             break 'main_loop;
         }
 
         ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
+    settings.record_result(&game)?;
     save_highscores_and_lines(&game.hiscores, &game.lines)?;
     Ok(())
 }
 
-fn handle_events(game: &mut Game, gw: &mut GameWindow) -> bool {
-    let mut quit = false;
+// sums the game up on the terminal when the player quits
+fn print_game_info(game: &Game) {
+    println!("Game over...");
+    if let Some(reason) = game.top_out {
+        println!("Reason: {}", reason.reason());
+    }
+    if matches!(game.current_state, GameState::Finished) {
+        println!("Time: {} ms", game.total_time_played);
+    }
+    println!("Score: {}", game.score);
+    println!("Number of lines: {}", game.lines_cleared);
+    println!("Current level: {}", game.level);
+}
 
-    let (mut dx, mut dy) = (0, 0);
+fn movement_input(keycode: Keycode) -> Option<Input> {
+    match keycode {
+        Keycode::Left => Some(Input::Left),
+        Keycode::Right => Some(Input::Right),
+        Keycode::Down => Some(Input::SoftDrop),
+        _ => None,
+    }
+}
+
+fn handle_events(
+    game: &mut Game,
+    gw: &mut GameWindow,
+    settings: &mut Settings,
+    input_state: &mut InputState,
+) -> Result<bool> {
+    let mut quit = false;
 
     'running: for event in gw.event_pump.poll_iter() {
         // This is always active
@@ -118,6 +175,20 @@ fn handle_events(game: &mut Game, gw: &mut GameWindow) -> bool {
                 quit = true;
                 break 'running;
             }
+            Event::KeyDown {
+                keycode: Some(Keycode::G),
+                ..
+            } => {
+                settings.ghost = !settings.ghost;
+            }
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => {
+                if let Some(input) = movement_input(keycode) {
+                    input_state.release(input);
+                }
+            }
             _ => {}
         }
 
@@ -133,30 +204,46 @@ fn handle_events(game: &mut Game, gw: &mut GameWindow) -> bool {
                         game.update_time();
                         game.current_state = GameState::Paused;
                     }
+                    // held keys repeat on the game's own clock, not the OS one
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        repeat: false,
+                        ..
+                    } if movement_input(keycode).is_some() => {
+                        let input = movement_input(keycode).unwrap();
+                        input_state.press(input, game);
+                    }
                     Event::KeyDown {
-                        keycode: Some(Keycode::Down),
+                        keycode: Some(Keycode::Up),
+                        ..
+                    }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::X),
                         ..
                     } => {
-                        gw.step_timer = SystemTime::now();
-                        dy += 1;
+                        game.rotate_piece(Rotation::Clockwise);
                     }
                     Event::KeyDown {
-                        keycode: Some(Keycode::Right),
+                        keycode: Some(Keycode::Z),
                         ..
                     } => {
-                        dx = 1;
+                        game.rotate_piece(Rotation::CounterClockwise);
                     }
                     Event::KeyDown {
-                        keycode: Some(Keycode::Left),
+                        keycode: Some(Keycode::A),
                         ..
                     } => {
-                        dx = -1;
+                        game.rotate_piece(Rotation::Half);
                     }
                     Event::KeyDown {
-                        keycode: Some(Keycode::Up),
+                        keycode: Some(Keycode::C),
+                        ..
+                    }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::LShift),
                         ..
                     } => {
-                        game.rotate_piece();
+                        game.hold_piece();
                     }
                     Event::KeyDown {
                         keycode: Some(Keycode::Space),
@@ -166,19 +253,11 @@ fn handle_events(game: &mut Game, gw: &mut GameWindow) -> bool {
                         keycode: Some(Keycode::Return),
                         ..
                     } => {
-                        // go as low as possible
-                        while game.change_piece_position(0, 1) {
-                            dy += 1;
-                        }
-                        gw.step_timer = SystemTime::now(); // resync timer
-                        game.piece.moves = game.fix_piece();
-                        return false;
+                        game.hard_drop();
+                        return Ok(false);
                     }
                     _ => {}
                 }
-                if !game.change_piece_position(dx, dy) && dy != 0 {
-                    game.piece.moves = false;
-                }
             }
             GameState::Paused | GameState::Start => {
                 if let Event::KeyDown {
@@ -190,34 +269,42 @@ fn handle_events(game: &mut Game, gw: &mut GameWindow) -> bool {
                     game.current_state = GameState::Playing;
                 }
             }
-            GameState::End => {
-                *game = Game::new();
+            GameState::End | GameState::Finished => {
+                // the result stays on screen until the player asks for a new game
+                if let Event::KeyDown {
+                    keycode: Some(Keycode::P | Keycode::Return),
+                    repeat: false,
+                    ..
+                } = event
+                {
+                    settings.record_result(game)?;
+                    *game = settings.new_game()?;
+                }
             }
         }
     }
 
-    quit
+    Ok(quit)
 }
 
+// draws the piece in its column at row `y` of the visible map, leaving out what is above it
 fn draw_current_piece(
     piece: &pieces::Piece,
+    y: isize,
     gw: &mut GameWindow,
     grid_x: i32,
     grid_y: i32,
     color_palette: &[Texture],
 ) -> Result<()> {
-    for (line_nb, line) in piece.shapes[piece.rot as usize].iter().enumerate() {
-        for i in 0..4 {
-            if line & (1 << i) == 0 {
-                continue;
-            }
-
-            gw.draw_tile(
-                grid_x + (piece.x + i as isize) as i32 * PIECE_SIZE as i32,
-                grid_y + (piece.y + line_nb) as i32 * PIECE_SIZE as i32,
-                &color_palette[piece.code as usize - 1],
-            )?;
+    for (x, row) in (pieces::Piece { y, ..*piece }).cells() {
+        if row < 0 {
+            continue;
         }
+        gw.draw_tile(
+            grid_x + x as i32 * gw.tile_size as i32,
+            grid_y + row as i32 * gw.tile_size as i32,
+            &color_palette[piece.kind.code() as usize - 1],
+        )?;
     }
     Ok(())
 }
@@ -229,12 +316,12 @@ fn draw_other_pieces(
     grid_y: i32,
     color_palette: &[Texture],
 ) -> Result<()> {
-    for (line_nb, line) in game.game_map.iter().enumerate() {
+    for (line_nb, line) in game.visible_map().enumerate() {
         for (case_nb, case) in line.iter().enumerate() {
             if *case != 0 {
                 gw.draw_tile(
-                    grid_x + case_nb as i32 * PIECE_SIZE as i32,
-                    grid_y + line_nb as i32 * PIECE_SIZE as i32,
+                    grid_x + case_nb as i32 * gw.tile_size as i32,
+                    grid_y + line_nb as i32 * gw.tile_size as i32,
                     &color_palette[*case as usize - 1],
                 )?;
             }
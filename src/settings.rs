@@ -0,0 +1,137 @@
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use engine::{modes::DIG_MESSINESS, Game, GameConfig, GameMode, GameState, Handling, Rules};
+
+use crate::{
+    fileio::{load_personal_best, save_personal_best},
+    gamewindow::max_board_size,
+    Result,
+};
+
+// options given on the command line
+pub struct Settings {
+    pub seed: Option<u64>,
+    pub config: GameConfig,
+    // show where the piece will land, toggled with G
+    pub ghost: bool,
+    pub handling: Handling,
+    // best result of the chosen mode, see `Game::result`
+    pub personal_best: Option<u128>,
+}
+
+impl Settings {
+    // parses `--mode <endless|sprint|ultra|marathon|zen|dig>`, `--goal <lines|seconds|rows>`,
+    // `--credits`, `--level <zen level>`, `--messiness <percent>`, `--feed`,
+    // `--seed <number>`, `--size <cols>x<rows>`, `--buffer <rows>`,
+    // `--randomizer <uniform|bag7|bag14|history|nes>`, `--preview <0-6>`, `--rotation <srs|ars>`, `--rules <file.toml>`,
+    // `--lock-reset <step|move|infinity>`, `--das <frames>`, `--arr <frames>`, `--sdf <factor>`,
+    // `--no-hold`, `--no-ghost`, `--no-block-out`, `--no-lock-out` and `--no-garbage-out`
+    pub fn from_args() -> Result<Settings> {
+        let mut settings = Settings {
+            seed: None,
+            config: GameConfig::default(),
+            ghost: true,
+            handling: Handling::default(),
+            personal_best: None,
+        };
+        let mut goal = None;
+        let mut credits = false;
+        let mut level = 1;
+        let (mut messiness, mut feed) = (DIG_MESSINESS, false);
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} expects a value", arg));
+            match arg.as_str() {
+                "--mode" => settings.config.mode = value()?.parse()?,
+                "--goal" => goal = Some(value()?.parse()?),
+                "--credits" => credits = true,
+                "--level" => level = value()?.parse()?,
+                "--messiness" => messiness = value()?.parse::<u32>()?.min(100),
+                "--feed" => feed = true,
+                "--seed" => settings.seed = Some(value()?.parse()?),
+                "--size" => {
+                    let size = value()?;
+                    let (cols, rows) = size
+                        .split_once('x')
+                        .ok_or(format!("--size expects <cols>x<rows>, got {}", size))?;
+                    settings.config.cols = cols.parse()?;
+                    settings.config.rows = rows.parse()?;
+                }
+                "--buffer" => settings.config.buffer_rows = value()?.parse()?,
+                "--randomizer" => settings.config.randomizer = value()?.parse()?,
+                "--preview" => settings.config.preview = value()?.parse()?,
+                "--rotation" => settings.config.rotation = value()?.parse()?,
+                "--rules" => settings.config.rules = Rules::load(&value()?)?,
+                "--lock-reset" => settings.config.lock_reset = value()?.parse()?,
+                "--das" => settings.handling.das = value()?.parse()?,
+                "--arr" => settings.handling.arr = value()?.parse()?,
+                "--sdf" => settings.handling.sdf = value()?.parse()?,
+                "--no-hold" => settings.config.hold = false,
+                "--no-ghost" => settings.ghost = false,
+                "--no-block-out" => settings.config.top_out.block_out = false,
+                "--no-lock-out" => settings.config.top_out.lock_out = false,
+                "--no-garbage-out" => settings.config.top_out.garbage_out = false,
+                _ => return Err(format!("unknown argument: {}", arg).into()),
+            }
+        }
+        if let Some(goal) = goal {
+            settings.config.mode = settings.config.mode.with_goal(goal);
+        }
+        match &mut settings.config.mode {
+            GameMode::Marathon { credits: roll, .. } => *roll = credits,
+            GameMode::Zen { level: zen_level } => *zen_level = level,
+            GameMode::Dig {
+                messiness: dig_messiness,
+                feed: dig_feed,
+                ..
+            } => {
+                *dig_messiness = messiness;
+                *dig_feed = feed;
+            }
+            _ => {}
+        }
+        settings.config.validate()?;
+        let (max_cols, max_rows) = max_board_size();
+        if settings.config.cols > max_cols || settings.config.rows > max_rows {
+            return Err(format!("the window shows at most {}x{} maps", max_cols, max_rows).into());
+        }
+        settings.personal_best = load_personal_best(&settings.config.mode.name());
+        Ok(settings)
+    }
+
+    // keeps the result of a finished game if it beats the personal best
+    pub fn record_result(&mut self, game: &Game) -> Result<()> {
+        if !matches!(game.current_state, GameState::Finished) {
+            return Ok(());
+        }
+        let result = game.result();
+        if self
+            .personal_best
+            .is_none_or(|best| game.config.mode.beats(result, best))
+        {
+            self.personal_best = Some(result);
+            save_personal_best(&game.config.mode.name(), result)?;
+        }
+        Ok(())
+    }
+
+    // starts a game with the fixed seed if one was given, a fresh one otherwise
+    pub fn new_game(&self) -> Result<Game> {
+        let seed = self.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let mut game = Game::new(GameConfig {
+            seed,
+            ..self.config.clone()
+        })?;
+        game.personal_best = self.personal_best;
+        Ok(game)
+    }
+}